@@ -0,0 +1,135 @@
+// Benchmarks for the processing pipeline over files from the `generate` command.
+// Inputs of 1K to 100K rows run by default. Set `RUST_LEDGER_BENCH_LARGE=1` to add
+// 1M and 10M rows, which take several minutes and a few gigabytes of memory.
+
+use std::hint::black_box;
+use criterion::{BenchmarkGroup, Criterion, Throughput, criterion_group, criterion_main, measurement::WallTime};
+use csv::{Reader, ReaderBuilder};
+use rust_ledger::columns::ColumnMap;
+use rust_ledger::config::Config;
+use rust_ledger::dialects::Dialect;
+use rust_ledger::generator::{GeneratorOptions, generate};
+use rust_ledger::ledger::{Ledger, process_transaction, summarize_reader};
+use rust_ledger::utils::{Transaction, process_row};
+
+fn sizes() -> Vec<u64> {
+    let mut sizes: Vec<u64> = vec![1_000, 10_000, 100_000];
+    if std::env::var("RUST_LEDGER_BENCH_LARGE").is_ok_and(|value| value == "1") {
+        sizes.extend([1_000_000, 10_000_000]);
+    }
+    sizes
+}
+
+fn generate_input(options: GeneratorOptions) -> Vec<u8> {
+    let mut contents: Vec<u8> = vec![];
+    generate(&options, &mut contents).unwrap();
+    contents
+}
+
+// Large inputs take seconds per run, so fewer samples are taken
+fn configure_group(group: &mut BenchmarkGroup<WallTime>, rows: u64) {
+    group.throughput(Throughput::Elements(rows));
+    group.sample_size(if rows >= 1_000_000 { 10 } else { 50 });
+}
+
+fn bench_parse_only(c: &mut Criterion) {
+    let mut group: BenchmarkGroup<WallTime> = c.benchmark_group("parse_only");
+    for rows in sizes() {
+        let contents: Vec<u8> = generate_input(GeneratorOptions { rows, ..Default::default() });
+        configure_group(&mut group, rows);
+        group.bench_function(rows.to_string(), |b| b.iter(|| {
+            let mut rdr: Reader<&[u8]> = ReaderBuilder::new().flexible(true).from_reader(contents.as_slice());
+            let mut parsed: usize = 0;
+            for record in rdr.records().flatten() {
+                parsed += usize::from(process_row(black_box(record), &ColumnMap::default(), &Dialect::default()).is_ok());
+            }
+            parsed
+        }));
+    }
+    group.finish();
+}
+
+fn bench_summarize(c: &mut Criterion, name: &str, options: impl Fn(u64) -> GeneratorOptions) {
+    let mut group: BenchmarkGroup<WallTime> = c.benchmark_group(name);
+    for rows in sizes() {
+        let contents: Vec<u8> = generate_input(options(rows));
+        configure_group(&mut group, rows);
+        group.bench_function(rows.to_string(), |b| b.iter(|| {
+            summarize_reader(black_box(contents.as_slice()), Config::default())
+        }));
+    }
+    group.finish();
+}
+
+fn bench_deposit_only(c: &mut Criterion) {
+    bench_summarize(c, "deposit_only", |rows| GeneratorOptions {
+        rows,
+        withdrawal_rate: 0.0,
+        dispute_rate: 0.0,
+        resolve_rate: 0.0,
+        chargeback_rate: 0.0,
+        malformed_rate: 0.0,
+        ..Default::default()
+    });
+}
+
+fn bench_dispute_heavy(c: &mut Criterion) {
+    bench_summarize(c, "dispute_heavy", |rows| GeneratorOptions {
+        rows,
+        withdrawal_rate: 0.1,
+        dispute_rate: 0.3,
+        resolve_rate: 0.2,
+        chargeback_rate: 0.001,
+        ..Default::default()
+    });
+}
+
+fn bench_many_clients(c: &mut Criterion) {
+    bench_summarize(c, "many_clients", |rows| GeneratorOptions {
+        rows,
+        clients: u16::MAX,
+        ..Default::default()
+    });
+}
+
+// Disputes and resolves naming a tx the client never made, which scan the whole
+// history before being rejected
+fn bench_dispute_search(c: &mut Criterion) {
+    let mut group: BenchmarkGroup<WallTime> = c.benchmark_group("dispute_search");
+    for rows in sizes().into_iter().filter(|rows| *rows <= 1_000_000) {
+        let contents: Vec<u8> = generate_input(GeneratorOptions {
+            clients: 1,
+            rows,
+            withdrawal_rate: 0.0,
+            dispute_rate: 0.0,
+            resolve_rate: 0.0,
+            chargeback_rate: 0.0,
+            malformed_rate: 0.0,
+            ..Default::default()
+        });
+        let mut ledger: Ledger = summarize_reader(contents.as_slice(), Config::default());
+        group.throughput(Throughput::Elements(rows));
+        for tx_type in ["dispute", "resolve"] {
+            group.bench_function(format!("{}/{}", tx_type, rows), |b| b.iter(|| {
+                let mut transaction: Transaction = Transaction {
+                    tx_type: tx_type.to_string(),
+                    client_id: 1,
+                    tx: u32::MAX,
+                    ..Default::default()
+                };
+                process_transaction(black_box(&mut transaction), &mut ledger)
+            }));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_parse_only,
+    bench_deposit_only,
+    bench_dispute_heavy,
+    bench_many_clients,
+    bench_dispute_search
+);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+#![no_main]
+
+use csv::ReaderBuilder;
+use libfuzzer_sys::fuzz_target;
+use rust_ledger::columns::ColumnMap;
+use rust_ledger::dialects::Dialect;
+use rust_ledger::utils::process_row;
+
+// Every record of the input is parsed on its own, as a row of a partner file would be
+fuzz_target!(|data: &[u8]| {
+    let mut rdr = ReaderBuilder::new().has_headers(false).flexible(true).from_reader(data);
+    for record in rdr.records().flatten() {
+        let _ = process_row(record, &ColumnMap::default(), &Dialect::default());
+    }
+});
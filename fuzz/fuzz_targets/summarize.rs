@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_ledger::config::{Config, InputFormat};
+use rust_ledger::ledger::summarize_reader;
+
+// Runs the whole pipeline three times: once with the defaults, once with reordering,
+// authorization expiry, strict checking and history pruning switched on, and once
+// reading the input as JSON lines
+fuzz_target!(|data: &[u8]| {
+    let _ = summarize_reader(data, Config::default());
+    let _ = summarize_reader(data, Config {
+        reorder_window: 4,
+        authorization_expiry_rows: Some(3),
+        authorization_expiry_secs: Some(60),
+        strict: true,
+        retain_rows: Some(8),
+        ..Default::default()
+    });
+    let _ = summarize_reader(data, Config {
+        input_format: InputFormat::JsonLines,
+        ..Default::default()
+    });
+});
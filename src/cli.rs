@@ -0,0 +1,619 @@
+use crate::columns::{self, COLUMNS};
+use crate::config::{Config, InputFormat, LockPolicy};
+use crate::dates::parse_date;
+use crate::dialects::{parse_character, parse_delimiter, parse_quote};
+use crate::generator::GeneratorOptions;
+use crate::rates::{MAX_DECIMALS, Rounding};
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Summarize,
+    Statement,
+    Journal,
+    TrialBalance,
+    Reconcile,
+    Disputes,
+    Generate,
+}
+
+pub struct Options {
+    pub command: Command,
+    pub file_path: String,
+    pub limits_path: Option<String>,
+    pub rates_path: Option<String>,
+    pub profiles_path: Option<String>,
+    pub profile: Option<String>,
+    pub rejections_path: Option<String>,
+    pub as_of: Option<i64>,
+    pub client: Option<u16>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub expected_path: Option<String>,
+    pub generator: GeneratorOptions,
+    pub config: Config,
+}
+
+pub const USAGE: &str = "Usage:
+  RustLedger [summarize] <transactions.csv> [options]
+  RustLedger statement <transactions.csv> --client <id> [--from <YYYY-MM-DD>] [--to <YYYY-MM-DD>] [options]
+  RustLedger journal <transactions.csv> [options]
+  RustLedger trial-balance <transactions.csv> [options]
+  RustLedger reconcile <transactions.csv> [options]
+  RustLedger disputes <transactions.csv> [--client <id>] [options]
+  RustLedger generate <output.csv> [--expected <file>] [generator options]
+
+Commands:
+  summarize      Print the balances of every client (default)
+  statement      Print a client's transactions with running balances
+  journal        Print the double-entry journal lines posted by every transaction
+  trial-balance  Print the net balance of every journal account
+  reconcile      Check balances against the history and journal, exiting with 1 on failure
+  disputes       Print the lifecycle of every dispute
+  generate       Write a synthetic transactions file and optionally the balances it should produce
+
+Options:
+  --format <csv|jsonl>                   Input format, by default jsonl for .jsonl and .ndjson files and csv otherwise
+  --column-alias <header>=<column>       Read a transactions header as one of the columns, e.g. txn_ref=tx
+  --delimiter <character>                Field delimiter: comma (default), semicolon, tab, pipe or any character
+  --quote <character|none>               Quote character (default \"), or none to read quotes as text
+  --comment <character>                  Skip lines starting with this character
+  --no-headers                           The transactions file has no header row
+  --decimal-comma                        Amounts use a comma as the decimal point, e.g. 100,50
+  --profiles <file> --profile <name>     Read the settings above from a named partner profile
+  --limits <file>                        Per-client overdraft and withdrawal limits
+  --rates <file>                         Dated exchange rates for convert rows
+  --fx-rounding <nearest|down|up|even>   How converted amounts are rounded (default nearest)
+  --fx-decimals <places>                 Decimal places of converted amounts (default 4)
+  --fx-spread <fraction>                 Fraction of each converted amount kept as a spread
+  --fx-fee <amount>                      Flat fee in the target currency taken from each conversion
+  --rejections <file>                    Write rejected rows and their reasons to a file
+  --authorization-expiry-rows <rows>     Release authorizations after this many rows
+  --authorization-expiry-secs <seconds>  Release authorizations after this long
+  --reorder-window <rows>                Re-sort rows by timestamp within this many rows
+  --dispute-window-rows <rows>           Reject disputes made more than this many rows after the deposit
+  --dispute-window-secs <seconds>        Reject disputes made more than this long after the deposit
+  --max-redisputes <count>               How often a resolved dispute may be reopened
+  --unlock-on-reversal                   Unlock an account once the chargeback that locked it is reversed
+  --lock-policy <policy>                 When chargebacks lock an account: always (default), never,
+                                         after:<count> or above:<amount>; others flag it
+  --strict                               Check invariants after every row and stop at the first violation
+  --retain-rows <rows>                   Bounded memory: drop history that later rows cannot refer to,
+                                         keeping deposits for this many rows
+  --spill-history <file>                 Write the history dropped by --retain-rows to a file
+  --as-of <YYYY-MM-DD>                   Report balances as of the end of a date (summarize only)
+
+Generator options:
+  --expected <file>                      Write the expected balances to a file
+  --clients <count>                      Number of clients (default 100)
+  --rows <count>                         Number of rows (default 10000)
+  --seed <number>                        Random seed (default 1)
+  --withdrawal-rate <fraction>           Share of withdrawal rows (default 0.3)
+  --dispute-rate <fraction>              Share of dispute rows (default 0.02)
+  --resolve-rate <fraction>              Share of resolve rows (default 0.01)
+  --chargeback-rate <fraction>           Share of chargeback rows (default 0.005)
+  --malformed-rate <fraction>            Share of rows that cannot be parsed (default 0.001)";
+
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut command: Command = Command::Summarize;
+    let mut file_path: Option<String> = None;
+    let mut limits_path: Option<String> = None;
+    let mut rates_path: Option<String> = None;
+    let mut profiles_path: Option<String> = None;
+    let mut profile: Option<String> = None;
+    let mut dialect_options: bool = false;
+    let mut generator_options: bool = false;
+    let mut engine_options: bool = false;
+    let mut input_format: Option<InputFormat> = None;
+    let mut rejections_path: Option<String> = None;
+    let mut as_of: Option<i64> = None;
+    let mut client: Option<u16> = None;
+    let mut from: Option<i64> = None;
+    let mut to: Option<i64> = None;
+    let mut expected_path: Option<String> = None;
+    let mut generator: GeneratorOptions = GeneratorOptions::default();
+    let mut config: Config = Config::default();
+    let mut remaining = args.iter().peekable();
+
+    if let Some(parsed_command) = remaining.peek().and_then(|first| parse_command(first)) {
+        command = parsed_command;
+        remaining.next();
+    }
+    while let Some(arg) = remaining.next() {
+        // Generator options only shape generated files, and the generator ignores the rest
+        let is_generator_option: bool = matches!(arg.as_str(), "--expected" | "--clients" | "--rows" | "--seed" | "--withdrawal-rate"
+            | "--dispute-rate" | "--resolve-rate" | "--chargeback-rate" | "--malformed-rate");
+        generator_options |= is_generator_option;
+        engine_options |= !is_generator_option && arg.starts_with("--");
+        match arg.as_str() {
+            "--format" => {
+                let value: String = next_value(arg, &mut remaining)?;
+                input_format = Some(InputFormat::parse(&value).map_err(|_| format!("Invalid value {} for {}", value, arg))?);
+            },
+            "--column-alias" => {
+                let (header, column) = parse_column_alias(arg, &next_value(arg, &mut remaining)?)?;
+                config.column_aliases.insert(header, column);
+            },
+            "--delimiter" => {
+                let value: String = next_value(arg, &mut remaining)?;
+                config.dialect.delimiter = parse_delimiter(&value).map_err(|_| format!("Invalid value {} for {}", value, arg))?;
+                dialect_options = true;
+            },
+            "--quote" => {
+                let value: String = next_value(arg, &mut remaining)?;
+                config.dialect.quote = parse_quote(&value).map_err(|_| format!("Invalid value {} for {}", value, arg))?;
+                dialect_options = true;
+            },
+            "--comment" => {
+                let value: String = next_value(arg, &mut remaining)?;
+                config.dialect.comment = Some(parse_character(&value).map_err(|_| format!("Invalid value {} for {}", value, arg))?);
+                dialect_options = true;
+            },
+            "--no-headers" => {
+                config.dialect.has_headers = false;
+                dialect_options = true;
+            },
+            "--decimal-comma" => {
+                config.dialect.decimal_comma = true;
+                dialect_options = true;
+            },
+            "--profiles" => profiles_path = Some(next_value(arg, &mut remaining)?),
+            "--profile" => profile = Some(next_value(arg, &mut remaining)?),
+            "--limits" => limits_path = Some(next_value(arg, &mut remaining)?),
+            "--rates" => rates_path = Some(next_value(arg, &mut remaining)?),
+            "--fx-rounding" => {
+                let value: String = next_value(arg, &mut remaining)?;
+                config.fx_rounding = Rounding::parse(&value).map_err(|_| format!("Invalid value {} for {}", value, arg))?;
+            },
+            "--fx-decimals" => {
+                let decimals: u32 = parse_number(arg, &next_value(arg, &mut remaining)?)?;
+                if decimals > MAX_DECIMALS {
+                    return Err(format!("{} must be at most {}", arg, MAX_DECIMALS));
+                }
+                config.fx_decimals = Some(decimals);
+            },
+            "--fx-spread" => {
+                config.fx_spread = parse_number(arg, &next_value(arg, &mut remaining)?)?;
+                if !(0.0..1.0).contains(&config.fx_spread) {
+                    return Err(format!("{} must be at least 0 and below 1", arg));
+                }
+            },
+            "--fx-fee" => {
+                config.fx_fee = parse_number(arg, &next_value(arg, &mut remaining)?)?;
+                if !config.fx_fee.is_finite() || config.fx_fee < 0.0 {
+                    return Err(format!("{} must be a non-negative amount", arg));
+                }
+            },
+            "--rejections" => rejections_path = Some(next_value(arg, &mut remaining)?),
+            "--authorization-expiry-rows" => {
+                config.authorization_expiry_rows = Some(parse_number(arg, &next_value(arg, &mut remaining)?)?);
+            },
+            "--authorization-expiry-secs" => {
+                config.authorization_expiry_secs = Some(parse_number(arg, &next_value(arg, &mut remaining)?)?);
+            },
+            "--reorder-window" => config.reorder_window = parse_number(arg, &next_value(arg, &mut remaining)?)?,
+            "--dispute-window-rows" => config.dispute_window_rows = Some(parse_number(arg, &next_value(arg, &mut remaining)?)?),
+            "--dispute-window-secs" => config.dispute_window_secs = Some(parse_number(arg, &next_value(arg, &mut remaining)?)?),
+            "--max-redisputes" => config.max_redisputes = Some(parse_number(arg, &next_value(arg, &mut remaining)?)?),
+            "--unlock-on-reversal" => config.unlock_on_reversal = true,
+            "--lock-policy" => {
+                let value: String = next_value(arg, &mut remaining)?;
+                config.lock_policy = LockPolicy::parse(&value).map_err(|_| format!("Invalid value {} for {}", value, arg))?;
+            },
+            "--strict" => config.strict = true,
+            "--retain-rows" => config.retain_rows = Some(parse_number(arg, &next_value(arg, &mut remaining)?)?),
+            "--spill-history" => config.spill_path = Some(next_value(arg, &mut remaining)?),
+            "--as-of" => as_of = Some(parse_date_value(arg, &next_value(arg, &mut remaining)?)?),
+            "--client" => client = Some(parse_number(arg, &next_value(arg, &mut remaining)?)?),
+            "--from" => from = Some(parse_date_value(arg, &next_value(arg, &mut remaining)?)?),
+            "--to" => to = Some(parse_date_value(arg, &next_value(arg, &mut remaining)?)?),
+            "--expected" => expected_path = Some(next_value(arg, &mut remaining)?),
+            "--clients" => generator.clients = parse_number(arg, &next_value(arg, &mut remaining)?)?,
+            "--rows" => generator.rows = parse_number(arg, &next_value(arg, &mut remaining)?)?,
+            "--seed" => generator.seed = parse_number(arg, &next_value(arg, &mut remaining)?)?,
+            "--withdrawal-rate" => generator.withdrawal_rate = parse_rate(arg, &next_value(arg, &mut remaining)?)?,
+            "--dispute-rate" => generator.dispute_rate = parse_rate(arg, &next_value(arg, &mut remaining)?)?,
+            "--resolve-rate" => generator.resolve_rate = parse_rate(arg, &next_value(arg, &mut remaining)?)?,
+            "--chargeback-rate" => generator.chargeback_rate = parse_rate(arg, &next_value(arg, &mut remaining)?)?,
+            "--malformed-rate" => generator.malformed_rate = parse_rate(arg, &next_value(arg, &mut remaining)?)?,
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            _ if file_path.is_none() => file_path = Some(arg.clone()),
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+
+    if generator_options && command != Command::Generate {
+        return Err("Generator options can only be used with the generate command".to_string());
+    }
+    if engine_options && command == Command::Generate {
+        return Err("The generate command only accepts generator options".to_string());
+    }
+    if command == Command::Statement && client.is_none() {
+        return Err("The statement command needs a --client".to_string());
+    }
+    if client.is_some() && !matches!(command, Command::Statement | Command::Disputes) {
+        return Err("--client can only be used with the statement and disputes commands".to_string());
+    }
+    if (from.is_some() || to.is_some()) && command != Command::Statement {
+        return Err("--from and --to can only be used with the statement command".to_string());
+    }
+    if as_of.is_some() && command != Command::Summarize {
+        return Err("--as-of can only be used with the summarize command".to_string());
+    }
+    let is_json_lines_file: bool = file_path.as_deref().is_some_and(|path| path.ends_with(".jsonl") || path.ends_with(".ndjson"));
+    config.input_format = input_format.unwrap_or(if is_json_lines_file { InputFormat::JsonLines } else { InputFormat::Csv });
+    if config.input_format == InputFormat::JsonLines && (dialect_options || profile.is_some()) {
+        return Err("CSV dialect options and profiles cannot be used with JSON-lines input".to_string());
+    }
+    if profiles_path.is_some() != profile.is_some() {
+        return Err("--profile and --profiles must be given together".to_string());
+    }
+    if profile.is_some() && dialect_options {
+        return Err("--profile cannot be combined with --delimiter, --quote, --comment, --no-headers or --decimal-comma".to_string());
+    }
+    if config.dialect.decimal_comma && config.dialect.delimiter == b',' {
+        return Err("--decimal-comma needs a --delimiter other than a comma".to_string());
+    }
+    if config.spill_path.is_some() && config.retain_rows.is_none() {
+        return Err("--spill-history needs --retain-rows".to_string());
+    }
+    if config.retain_rows.is_some() && (matches!(command, Command::Statement | Command::Journal | Command::Disputes) || as_of.is_some()) {
+        return Err("Statements, the journal, the disputes command and --as-of need the full history and cannot be used with --retain-rows".to_string());
+    }
+    if config.retain_rows.is_some() && rejections_path.is_some() {
+        return Err("--rejections cannot be used with --retain-rows".to_string());
+    }
+    if generator.withdrawal_rate + generator.dispute_rate + generator.resolve_rate + generator.chargeback_rate > 1.0 {
+        return Err("The generator rates add up to more than 1".to_string());
+    }
+
+    Ok(Options {
+        command,
+        file_path: file_path.ok_or("Missing transactions file")?,
+        limits_path,
+        rates_path,
+        profiles_path,
+        profile,
+        rejections_path,
+        as_of,
+        client,
+        from,
+        to,
+        expected_path,
+        generator,
+        config,
+    })
+}
+
+fn parse_command(name: &str) -> Option<Command> {
+    match name {
+        "summarize" => Some(Command::Summarize),
+        "statement" => Some(Command::Statement),
+        "journal" => Some(Command::Journal),
+        "trial-balance" => Some(Command::TrialBalance),
+        "reconcile" => Some(Command::Reconcile),
+        "disputes" => Some(Command::Disputes),
+        "generate" => Some(Command::Generate),
+        _ => None,
+    }
+}
+
+fn next_value<'a>(flag: &str, remaining: &mut impl Iterator<Item = &'a String>) -> Result<String, String> {
+    match remaining.next() {
+        Some(value) => Ok(value.clone()),
+        None => Err(format!("Missing value for {}", flag)),
+    }
+}
+
+fn parse_date_value(flag: &str, value: &str) -> Result<i64, String> {
+    parse_date(value).map_err(|_| format!("Invalid date {} for {}, expected YYYY-MM-DD", value, flag))
+}
+
+fn parse_rate(flag: &str, value: &str) -> Result<f64, String> {
+    let rate: f64 = parse_number(flag, value)?;
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(format!("{} must be between 0 and 1", flag));
+    }
+    Ok(rate)
+}
+
+fn parse_column_alias(flag: &str, value: &str) -> Result<(String, String), String> {
+    columns::parse_column_alias(value)
+        .map_err(|_| format!("Invalid value {} for {}, expected <header>=<column> with a column of {}", value, flag, COLUMNS.join(", ")))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value {} for {}", value, flag))
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+    use crate::dialects::Dialect;
+
+    fn to_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args_file_only() {
+        let options: Options = parse_args(&to_args(&["transactions.csv"])).unwrap();
+
+        assert_eq!(options.file_path, "transactions.csv");
+        assert_eq!(options.command, Command::Summarize);
+        assert!(options.limits_path.is_none());
+    }
+
+    #[test]
+    fn test_parse_args_limits() {
+        let options: Options = parse_args(&to_args(&["--limits", "limits.csv", "transactions.csv"])).unwrap();
+
+        assert_eq!(options.file_path, "transactions.csv");
+        assert_eq!(options.limits_path, Some("limits.csv".to_string()));
+    }
+
+    #[test]
+    fn test_parse_args_column_aliases() {
+        let options: Options = parse_args(&to_args(&[
+            "transactions.csv",
+            "--column-alias",
+            "Txn_Ref=tx",
+            "--column-alias",
+            "posted_at=timestamp",
+        ])).unwrap();
+
+        assert_eq!(options.config.column_aliases.get("txn_ref"), Some(&"tx".to_string()));
+        assert_eq!(options.config.column_aliases.get("posted_at"), Some(&"timestamp".to_string()));
+        assert!(parse_args(&to_args(&["transactions.csv", "--column-alias", "txn_ref"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--column-alias", "txn_ref=reference"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_dialect() {
+        let options: Options = parse_args(&to_args(&[
+            "transactions.csv",
+            "--delimiter",
+            "semicolon",
+            "--quote",
+            "none",
+            "--comment",
+            "#",
+            "--no-headers",
+            "--decimal-comma",
+        ])).unwrap();
+
+        assert_eq!(options.config.dialect, Dialect {
+            delimiter: b';',
+            quote: None,
+            comment: Some(b'#'),
+            has_headers: false,
+            decimal_comma: true,
+        });
+
+        let options: Options = parse_args(&to_args(&["transactions.csv", "--profiles", "profiles.csv", "--profile", "nordbank"])).unwrap();
+        assert_eq!(options.profiles_path, Some("profiles.csv".to_string()));
+        assert_eq!(options.profile, Some("nordbank".to_string()));
+
+        assert!(parse_args(&to_args(&["transactions.csv", "--delimiter", "::"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--decimal-comma"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--delimiter", "comma", "--decimal-comma"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--profile", "nordbank"])).is_err());
+        assert!(parse_args(&to_args(&[
+            "transactions.csv",
+            "--profiles",
+            "profiles.csv",
+            "--profile",
+            "nordbank",
+            "--decimal-comma",
+        ])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_input_format() {
+        assert_eq!(parse_args(&to_args(&["transactions.csv"])).unwrap().config.input_format, InputFormat::Csv);
+        assert_eq!(parse_args(&to_args(&["events.jsonl"])).unwrap().config.input_format, InputFormat::JsonLines);
+        assert_eq!(
+            parse_args(&to_args(&["events.txt", "--format", "jsonl"])).unwrap().config.input_format,
+            InputFormat::JsonLines
+        );
+        assert_eq!(
+            parse_args(&to_args(&["events.ndjson", "--format", "csv"])).unwrap().config.input_format,
+            InputFormat::Csv
+        );
+        assert!(parse_args(&to_args(&["events.txt", "--format", "xml"])).is_err());
+        assert!(parse_args(&to_args(&["events.jsonl", "--delimiter", "tab"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_rejections() {
+        let options: Options = parse_args(&to_args(&["transactions.csv", "--rejections", "rejections.csv"])).unwrap();
+
+        assert_eq!(options.rejections_path, Some("rejections.csv".to_string()));
+    }
+
+    #[test]
+    fn test_parse_args_summarize_as_of() {
+        let options: Options = parse_args(&to_args(&["summarize", "transactions.csv", "--as-of", "2026-09-30"])).unwrap();
+
+        assert_eq!(options.command, Command::Summarize);
+        assert_eq!(options.file_path, "transactions.csv");
+        assert_eq!(options.as_of, Some(20_726));
+    }
+
+    #[test]
+    fn test_parse_args_statement() {
+        let options: Options = parse_args(&to_args(&[
+            "statement",
+            "transactions.csv",
+            "--client",
+            "1001",
+            "--from",
+            "2026-09-01",
+            "--to",
+            "2026-09-30",
+        ])).unwrap();
+
+        assert_eq!(options.command, Command::Statement);
+        assert_eq!(options.client, Some(1001));
+        assert_eq!(options.from, Some(20_697));
+        assert_eq!(options.to, Some(20_726));
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse_args(&to_args(&["statement", "transactions.csv"])).is_err());
+        assert!(parse_args(&to_args(&["statement", "transactions.csv", "--client", "70000"])).is_err());
+        assert!(parse_args(&to_args(&["summarize"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--as-of", "30/09/2026"])).is_err());
+        assert!(parse_args(&to_args(&[])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--limits"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--unknown"])).is_err());
+        assert!(parse_args(&to_args(&["a.csv", "b.csv"])).is_err());
+        assert!(parse_args(&to_args(&["a.csv", "--authorization-expiry-rows", "abc"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--client", "1"])).is_err());
+        assert!(parse_args(&to_args(&["journal", "transactions.csv", "--client", "1"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--from", "2026-09-01"])).is_err());
+        assert!(parse_args(&to_args(&["disputes", "transactions.csv", "--to", "2026-09-30"])).is_err());
+        assert!(parse_args(&to_args(&["journal", "transactions.csv", "--as-of", "2026-09-30"])).is_err());
+        assert!(parse_args(&to_args(&["statement", "transactions.csv", "--client", "1", "--as-of", "2026-09-30"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_authorization_expiry() {
+        let options: Options = parse_args(&to_args(&["transactions.csv", "--authorization-expiry-rows", "10"])).unwrap();
+
+        assert_eq!(options.config.authorization_expiry_rows, Some(10));
+    }
+
+    #[test]
+    fn test_parse_args_timestamps() {
+        let options: Options = parse_args(&to_args(&[
+            "transactions.csv",
+            "--authorization-expiry-secs",
+            "3600",
+            "--reorder-window",
+            "5",
+        ])).unwrap();
+
+        assert_eq!(options.config.authorization_expiry_secs, Some(3600));
+        assert_eq!(options.config.reorder_window, 5);
+    }
+
+    #[test]
+    fn test_parse_args_strict() {
+        assert!(parse_args(&to_args(&["transactions.csv", "--strict"])).unwrap().config.strict);
+        assert!(!parse_args(&to_args(&["transactions.csv"])).unwrap().config.strict);
+    }
+
+    #[test]
+    fn test_parse_args_dispute_window() {
+        let options: Options = parse_args(&to_args(&[
+            "transactions.csv",
+            "--dispute-window-rows",
+            "500",
+            "--dispute-window-secs",
+            "86400",
+        ])).unwrap();
+
+        assert_eq!(options.config.dispute_window_rows, Some(500));
+        assert_eq!(options.config.dispute_window_secs, Some(86400));
+        assert_eq!(parse_args(&to_args(&["transactions.csv", "--max-redisputes", "2"])).unwrap().config.max_redisputes, Some(2));
+        assert!(parse_args(&to_args(&["transactions.csv", "--unlock-on-reversal"])).unwrap().config.unlock_on_reversal);
+        assert_eq!(
+            parse_args(&to_args(&["transactions.csv", "--lock-policy", "after:2"])).unwrap().config.lock_policy,
+            LockPolicy::AfterChargebacks(2)
+        );
+        assert!(parse_args(&to_args(&["transactions.csv", "--lock-policy", "later"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--dispute-window-rows", "-1"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_bounded_memory() {
+        let options: Options = parse_args(&to_args(&[
+            "transactions.csv",
+            "--retain-rows",
+            "1000",
+            "--spill-history",
+            "history.csv",
+        ])).unwrap();
+
+        assert_eq!(options.config.retain_rows, Some(1000));
+        assert_eq!(options.config.spill_path, Some("history.csv".to_string()));
+        assert!(parse_args(&to_args(&["transactions.csv", "--spill-history", "history.csv"])).is_err());
+        assert!(parse_args(&to_args(&["journal", "transactions.csv", "--retain-rows", "10"])).is_err());
+        assert!(parse_args(&to_args(&["disputes", "transactions.csv", "--retain-rows", "10"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--retain-rows", "10", "--as-of", "2024-01-01"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--retain-rows", "10", "--rejections", "rejected.csv"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_fx() {
+        let options: Options = parse_args(&to_args(&[
+            "transactions.csv",
+            "--rates",
+            "rates.csv",
+            "--fx-rounding",
+            "down",
+            "--fx-decimals",
+            "2",
+            "--fx-spread",
+            "0.01",
+            "--fx-fee",
+            "0.5",
+        ])).unwrap();
+
+        assert_eq!(options.rates_path, Some("rates.csv".to_string()));
+        assert_eq!(options.config.fx_rounding, Rounding::Down);
+        assert_eq!(options.config.fx_decimals, Some(2));
+        assert_eq!(options.config.fx_spread, 0.01);
+        assert_eq!(options.config.fx_fee, 0.5);
+        assert!(parse_args(&to_args(&["transactions.csv", "--fx-rounding", "sideways"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--fx-spread", "1.5"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--fx-decimals", "9"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--fx-fee", "-1"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_journal_commands() {
+        assert_eq!(parse_args(&to_args(&["journal", "transactions.csv"])).unwrap().command, Command::Journal);
+        assert_eq!(parse_args(&to_args(&["trial-balance", "transactions.csv"])).unwrap().command, Command::TrialBalance);
+        assert_eq!(parse_args(&to_args(&["reconcile", "transactions.csv"])).unwrap().command, Command::Reconcile);
+        assert_eq!(parse_args(&to_args(&["disputes", "transactions.csv"])).unwrap().command, Command::Disputes);
+    }
+
+    #[test]
+    fn test_parse_args_generate() {
+        let options: Options = parse_args(&to_args(&[
+            "generate",
+            "generated.csv",
+            "--expected",
+            "expected.csv",
+            "--clients",
+            "10",
+            "--rows",
+            "500",
+            "--seed",
+            "42",
+            "--dispute-rate",
+            "0.1",
+            "--malformed-rate",
+            "0",
+        ])).unwrap();
+
+        assert_eq!(options.command, Command::Generate);
+        assert_eq!(options.file_path, "generated.csv");
+        assert_eq!(options.expected_path, Some("expected.csv".to_string()));
+        assert_eq!(options.generator.clients, 10);
+        assert_eq!(options.generator.rows, 500);
+        assert_eq!(options.generator.seed, 42);
+        assert_eq!(options.generator.dispute_rate, 0.1);
+        assert_eq!(options.generator.malformed_rate, 0.0);
+        assert!(parse_args(&to_args(&["generate", "out.csv", "--dispute-rate", "2"])).is_err());
+        assert!(parse_args(&to_args(&["generate", "out.csv", "--withdrawal-rate", "0.9", "--dispute-rate", "0.2"])).is_err());
+        assert!(parse_args(&to_args(&["transactions.csv", "--rows", "10"])).is_err());
+        assert!(parse_args(&to_args(&["journal", "transactions.csv", "--expected", "expected.csv"])).is_err());
+        assert!(parse_args(&to_args(&["generate", "out.csv", "--strict"])).is_err());
+        assert!(parse_args(&to_args(&["generate", "out.csv", "--lock-policy", "never"])).is_err());
+        assert!(parse_args(&to_args(&["generate", "out.csv", "--as-of", "2026-09-30"])).is_err());
+        assert!(parse_args(&to_args(&["generate", "out.csv", "--limits", "nope.csv"])).is_err());
+    }
+}
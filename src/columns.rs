@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use csv::StringRecord;
+
+// The transaction columns in their positional order. The first four are required.
+pub const COLUMNS: [&str; 7] = ["type", "client", "tx", "amount", "timestamp", "currency", "target_currency"];
+const REQUIRED_COLUMNS: usize = 4;
+
+// Header names commonly used for the columns in partner files
+const BUILTIN_ALIASES: [(&str, &str); 6] = [
+    ("tx_type", "type"),
+    ("transaction_type", "type"),
+    ("client_id", "client"),
+    ("txn", "tx"),
+    ("tx_id", "tx"),
+    ("transaction_id", "tx"),
+];
+
+// Where each field of a transaction is found in a row
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnMap {
+    pub tx_type: usize,
+    pub client: usize,
+    pub tx: usize,
+    pub amount: usize,
+    pub timestamp: Option<usize>,
+    pub currency: Option<usize>,
+    pub target_currency: Option<usize>,
+}
+
+impl Default for ColumnMap {
+    fn default() -> Self {
+        ColumnMap {
+            tx_type: 0,
+            client: 1,
+            tx: 2,
+            amount: 3,
+            timestamp: Some(4),
+            currency: Some(5),
+            target_currency: Some(6),
+        }
+    }
+}
+
+impl ColumnMap {
+    // Columns are looked up by header name, ignoring case, or by an alias in `aliases`
+    // (header to column) or the built-in ones. Optional columns the header does not
+    // name keep their position when the header stops short of it, so rows may still
+    // carry a trailing timestamp or currency under a `type,client,tx,amount` header.
+    pub fn from_headers(headers: &StringRecord, aliases: &HashMap<String, String>) -> Result<ColumnMap, String> {
+        let mut positions: [Option<usize>; COLUMNS.len()] = [None; COLUMNS.len()];
+        for (index, header) in headers.iter().enumerate() {
+            if let Some(column) = column_for_header(header, aliases)
+                && positions[column].is_none() {
+                    positions[column] = Some(index);
+                }
+        }
+        for (column, position) in positions.iter_mut().enumerate() {
+            if position.is_none() && column >= REQUIRED_COLUMNS && headers.len() <= column {
+                *position = Some(column);
+            }
+        }
+        if let Some(column) = (0..REQUIRED_COLUMNS).find(|column| positions[*column].is_none()) {
+            return Err(format!("no {} column", COLUMNS[column]));
+        }
+
+        Ok(ColumnMap {
+            tx_type: positions[0].unwrap(),
+            client: positions[1].unwrap(),
+            tx: positions[2].unwrap(),
+            amount: positions[3].unwrap(),
+            timestamp: positions[4],
+            currency: positions[5],
+            target_currency: positions[6],
+        })
+    }
+}
+
+// An alias written as `<header>=<column>`, returned with the header lowercased
+pub fn parse_column_alias(value: &str) -> Result<(String, String), ()> {
+    match value.split_once('=') {
+        Some((header, column)) if !header.trim().is_empty() && COLUMNS.contains(&column.trim()) => {
+            Ok((header.trim().to_ascii_lowercase(), column.trim().to_string()))
+        },
+        _ => Err(()),
+    }
+}
+
+// The index in `COLUMNS` of the column a header or JSON key stands for
+pub fn column_for_header(header: &str, aliases: &HashMap<String, String>) -> Option<usize> {
+    let header: String = header.trim().to_ascii_lowercase();
+    let name: &str = match aliases.get(&header) {
+        Some(column) => column,
+        None => BUILTIN_ALIASES.iter()
+            .find(|(alias, _)| *alias == header)
+            .map_or(header.as_str(), |(_, column)| column),
+    };
+    COLUMNS.iter().position(|column| *column == name)
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_from_headers_reordered_with_aliases() {
+        let headers: StringRecord = StringRecord::from(vec!["Amount", "txn", "note", "client_id", "TYPE", "currency"]);
+        let columns: ColumnMap = ColumnMap::from_headers(&headers, &HashMap::new()).unwrap();
+
+        assert_eq!(columns, ColumnMap {
+            tx_type: 4,
+            client: 3,
+            tx: 1,
+            amount: 0,
+            timestamp: None,
+            currency: Some(5),
+            target_currency: Some(6),
+        });
+    }
+
+    #[test]
+    fn test_from_headers_configured_aliases() {
+        let aliases: HashMap<String, String> = HashMap::from([
+            ("reference".to_string(), "tx".to_string()),
+            ("posted_at".to_string(), "timestamp".to_string()),
+        ]);
+        let headers: StringRecord = StringRecord::from(vec!["type", "client", "reference", "amount", "posted_at"]);
+        let columns: ColumnMap = ColumnMap::from_headers(&headers, &aliases).unwrap();
+
+        assert_eq!((columns.tx, columns.timestamp), (2, Some(4)));
+    }
+
+    #[test]
+    fn test_from_headers_keeps_trailing_positions() {
+        let headers: StringRecord = StringRecord::from(vec!["type", "client", "tx", "amount"]);
+
+        assert_eq!(ColumnMap::from_headers(&headers, &HashMap::new()).unwrap(), ColumnMap::default());
+    }
+
+    #[test]
+    fn test_from_headers_missing_column() {
+        let headers: StringRecord = StringRecord::from(vec!["type", "client", "amount"]);
+
+        assert_eq!(ColumnMap::from_headers(&headers, &HashMap::new()), Err("no tx column".to_string()));
+    }
+}
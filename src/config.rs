@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use csv::{ReaderBuilder, Reader, StringRecord};
+use crate::dialects::Dialect;
+use crate::rates::{RateTable, Rounding};
+use crate::utils::{get_float, get_int_u16, get_int_u32};
+
+#[derive(Clone, Default)]
+pub struct AccountLimits {
+    pub overdraft: f32,
+    pub max_withdrawal: Option<f32>,
+    pub max_daily_withdrawal: Option<f32>,
+    pub max_withdrawals: Option<u32>,
+    pub withdrawal_window: Option<u64>,
+}
+
+// When a chargeback locks the account. Chargebacks that do not lock it flag it instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LockPolicy {
+    #[default]
+    Always,
+    // Locks once the given number of deposits are charged back
+    AfterChargebacks(u32),
+    // Locks on chargebacks of more than the given amount
+    AboveAmount(f32),
+    Never,
+}
+
+impl LockPolicy {
+    // One of `always`, `never`, `after:<count>` or `above:<amount>`
+    pub fn parse(value: &str) -> Result<LockPolicy, ()> {
+        match value.split_once(':') {
+            None if value == "always" => Ok(LockPolicy::Always),
+            None if value == "never" => Ok(LockPolicy::Never),
+            Some(("after", count)) => match count.trim().parse::<u32>() {
+                Ok(count) if count > 0 => Ok(LockPolicy::AfterChargebacks(count)),
+                _ => Err(()),
+            },
+            Some(("above", amount)) => match amount.trim().parse::<f32>() {
+                Ok(amount) if amount >= 0.0 && amount.is_finite() => Ok(LockPolicy::AboveAmount(amount)),
+                _ => Err(()),
+            },
+            _ => Err(()),
+        }
+    }
+
+    // Whether a chargeback of `amount` locks the account, with `chargebacks` deposits
+    // charged back including this one
+    pub fn locks(&self, chargebacks: u32, amount: f32) -> bool {
+        match self {
+            LockPolicy::Always => true,
+            LockPolicy::AfterChargebacks(count) => chargebacks >= *count,
+            LockPolicy::AboveAmount(threshold) => amount > *threshold,
+            LockPolicy::Never => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum InputFormat {
+    #[default]
+    Csv,
+    // One JSON object per line
+    JsonLines,
+}
+
+impl InputFormat {
+    pub fn parse(value: &str) -> Result<InputFormat, ()> {
+        match value {
+            "csv" => Ok(InputFormat::Csv),
+            "jsonl" | "ndjson" => Ok(InputFormat::JsonLines),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Config {
+    pub limits: HashMap<u16, AccountLimits>,
+    pub authorization_expiry_rows: Option<u64>,
+    pub authorization_expiry_secs: Option<i64>,
+    pub reorder_window: usize,
+    pub dispute_window_rows: Option<u64>,
+    pub dispute_window_secs: Option<i64>,
+    pub max_redisputes: Option<u32>,
+    pub unlock_on_reversal: bool,
+    pub lock_policy: LockPolicy,
+    pub rates: RateTable,
+    pub fx_rounding: Rounding,
+    pub fx_decimals: Option<u32>,
+    pub fx_spread: f32,
+    // Flat fee in the target currency taken from every conversion
+    pub fx_fee: f32,
+    pub strict: bool,
+    pub retain_rows: Option<u64>,
+    pub spill_path: Option<String>,
+    // Extra transaction header names, lowercased, and the column each one stands for
+    pub column_aliases: HashMap<String, String>,
+    pub dialect: Dialect,
+    pub input_format: InputFormat,
+}
+
+impl Config {
+    pub fn limits_for(&self, client_id: u16) -> AccountLimits {
+        self.limits.get(&client_id).cloned().unwrap_or_default()
+    }
+}
+
+struct LimitColumns {
+    client: usize,
+    overdraft: Option<usize>,
+    max_withdrawal: Option<usize>,
+    max_daily_withdrawal: Option<usize>,
+    max_withdrawals: Option<usize>,
+    withdrawal_window: Option<usize>,
+}
+
+// Limits are looked up by header name so that they can live in their own file
+// or as extra columns of an accounts file, e.g. `client,...,overdraft_limit`
+pub fn load_limits(file_path: &str) -> Result<HashMap<u16, AccountLimits>, String> {
+    let mut rdr: Reader<std::fs::File> = ReaderBuilder::new()
+        .from_path(file_path)
+        .map_err(|err| format!("Unable to read limits file {}: {}", file_path, err))?;
+    let headers: StringRecord = rdr.headers()
+        .map_err(|err| format!("Unable to read limits file {}: {}", file_path, err))?
+        .clone();
+    let columns: LimitColumns = LimitColumns {
+        client: column_index(&headers, "client")
+            .ok_or(format!("Limits file {} has no client column", file_path))?,
+        overdraft: column_index(&headers, "overdraft_limit"),
+        max_withdrawal: column_index(&headers, "max_withdrawal"),
+        max_daily_withdrawal: column_index(&headers, "max_daily_withdrawal"),
+        max_withdrawals: column_index(&headers, "max_withdrawals"),
+        withdrawal_window: column_index(&headers, "withdrawal_window"),
+    };
+    let mut limits: HashMap<u16, AccountLimits> = HashMap::new();
+
+    for result in rdr.records() {
+        let record: StringRecord = match result {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+        let client_id: u16 = match get_int_u16(&record, columns.client) {
+            Ok(client_id) => client_id,
+            Err(_) => continue,
+        };
+        match parse_limits(&record, &columns) {
+            Ok(client_limits) => limits.insert(client_id, client_limits),
+            Err(_) => continue,
+        };
+    }
+
+    Ok(limits)
+}
+
+fn parse_limits(record: &StringRecord, columns: &LimitColumns) -> Result<AccountLimits, ()> {
+    Ok(AccountLimits {
+        overdraft: get_optional_float(record, columns.overdraft)?.unwrap_or(0.0),
+        max_withdrawal: get_optional_float(record, columns.max_withdrawal)?,
+        max_daily_withdrawal: get_optional_float(record, columns.max_daily_withdrawal)?,
+        max_withdrawals: get_optional_int(record, columns.max_withdrawals)?,
+        withdrawal_window: get_optional_int(record, columns.withdrawal_window)?.map(u64::from),
+    })
+}
+
+fn get_optional_float(record: &StringRecord, index: Option<usize>) -> Result<Option<f32>, ()> {
+    match index {
+        Some(index) => get_float(record, index),
+        None => Ok(None),
+    }
+}
+
+fn get_optional_int(record: &StringRecord, index: Option<usize>) -> Result<Option<u32>, ()> {
+    match index {
+        Some(index) if record.get(index).is_some_and(|value| !value.trim().is_empty()) => {
+            get_int_u32(record, index).map(Some)
+        },
+        _ => Ok(None),
+    }
+}
+
+fn column_index(headers: &StringRecord, name: &str) -> Option<usize> {
+    headers.iter().position(|header| header.trim() == name)
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    fn write_temp_file(name: &str, contents: &str) -> String {
+        let path: std::path::PathBuf = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_load_limits() {
+        let file_path: String = write_temp_file(
+            "rust_ledger_test_load_limits.csv",
+            "client,overdraft_limit\n1,50.0\n2,\nabc,10.0\n3,-5.0\n",
+        );
+        let limits: HashMap<u16, AccountLimits> = load_limits(&file_path).unwrap();
+
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits.get(&1).unwrap().overdraft, 50.0);
+        assert_eq!(limits.get(&2).unwrap().overdraft, 0.0);
+    }
+
+    #[test]
+    fn test_load_limits_from_accounts_file() {
+        let file_path: String = write_temp_file(
+            "rust_ledger_test_load_limits_accounts.csv",
+            "client,available,held,total,locked,overdraft_limit\n7,10.0,0.0,10.0,false,25.5\n",
+        );
+        let limits: HashMap<u16, AccountLimits> = load_limits(&file_path).unwrap();
+
+        assert_eq!(limits.get(&7).unwrap().overdraft, 25.5);
+    }
+
+    #[test]
+    fn test_load_limits_velocity_columns() {
+        let file_path: String = write_temp_file(
+            "rust_ledger_test_load_limits_velocity.csv",
+            "client,max_withdrawal,max_daily_withdrawal,max_withdrawals,withdrawal_window\n1,100,250.5,3,10\n2,,,,\n3,,,x,\n",
+        );
+        let limits: HashMap<u16, AccountLimits> = load_limits(&file_path).unwrap();
+        let client_limits: &AccountLimits = limits.get(&1).unwrap();
+
+        assert_eq!(limits.len(), 2);
+        assert_eq!(client_limits.overdraft, 0.0);
+        assert_eq!(client_limits.max_withdrawal, Some(100.0));
+        assert_eq!(client_limits.max_daily_withdrawal, Some(250.5));
+        assert_eq!(client_limits.max_withdrawals, Some(3));
+        assert_eq!(client_limits.withdrawal_window, Some(10));
+        assert_eq!(limits.get(&2).unwrap().max_withdrawals, None);
+    }
+
+    #[test]
+    fn test_load_limits_missing_client_column() {
+        let file_path: String = write_temp_file(
+            "rust_ledger_test_load_limits_no_client.csv",
+            "id,overdraft_limit\n1,50.0\n",
+        );
+
+        assert!(load_limits(&file_path).is_err());
+    }
+
+    #[test]
+    fn test_limits_for_unknown_client() {
+        let config: Config = Config::default();
+
+        assert_eq!(config.limits_for(1).overdraft, 0.0);
+    }
+
+    #[test]
+    fn test_lock_policy() {
+        assert_eq!(LockPolicy::parse("always"), Ok(LockPolicy::Always));
+        assert_eq!(LockPolicy::parse("never"), Ok(LockPolicy::Never));
+        assert_eq!(LockPolicy::parse("after:3"), Ok(LockPolicy::AfterChargebacks(3)));
+        assert_eq!(LockPolicy::parse("above:250.5"), Ok(LockPolicy::AboveAmount(250.5)));
+        assert!(LockPolicy::parse("after:0").is_err());
+        assert!(LockPolicy::parse("above:-1").is_err());
+        assert!(LockPolicy::parse("sometimes").is_err());
+
+        assert!(!LockPolicy::AfterChargebacks(3).locks(2, 10.0));
+        assert!(LockPolicy::AfterChargebacks(3).locks(3, 10.0));
+        assert!(!LockPolicy::AboveAmount(100.0).locks(1, 100.0));
+        assert!(LockPolicy::AboveAmount(100.0).locks(1, 100.5));
+        assert!(!LockPolicy::Never.locks(5, 1000.0));
+    }
+}
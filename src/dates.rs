@@ -0,0 +1,154 @@
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+// Accepts unix seconds, `YYYY-MM-DD`, or `YYYY-MM-DDTHH:MM[:SS][Z]` (a space may
+// be used in place of the `T`). All times are taken to be UTC.
+pub fn parse_timestamp(value: &str) -> Result<i64, ()> {
+    let value: &str = value.trim();
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Ok(seconds);
+    }
+    if value.len() < 10 || !value.is_char_boundary(10) {
+        return Err(());
+    }
+    let (date, time) = value.split_at(10);
+    let days: i64 = parse_date(date)?;
+    let time: &str = time.strip_suffix('Z').unwrap_or(time);
+    let seconds_in_day: i64 = match time.strip_prefix(['T', ' ']) {
+        Some(time) => parse_time(time)?,
+        None if time.is_empty() => 0,
+        None => return Err(()),
+    };
+
+    Ok(days * SECONDS_PER_DAY + seconds_in_day)
+}
+
+// Returns the number of days since 1970-01-01 for a `YYYY-MM-DD` date
+pub fn parse_date(value: &str) -> Result<i64, ()> {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return Err(());
+    }
+    let year: i64 = parse_digits(parts[0])?;
+    let month: i64 = parse_digits(parts[1])?;
+    let day: i64 = parse_digits(parts[2])?;
+    let days: i64 = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return Err(());
+    }
+
+    Ok(days)
+}
+
+pub fn day_of(timestamp: i64) -> i64 {
+    timestamp.div_euclid(SECONDS_PER_DAY)
+}
+
+pub fn format_timestamp(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(day_of(timestamp));
+    let seconds_in_day: i64 = timestamp.rem_euclid(SECONDS_PER_DAY);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_in_day / 3600,
+        seconds_in_day % 3600 / 60,
+        seconds_in_day % 60
+    )
+}
+
+fn parse_time(value: &str) -> Result<i64, ()> {
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 || parts.iter().any(|part| part.len() != 2) {
+        return Err(());
+    }
+    let hours: i64 = parse_digits(parts[0])?;
+    let minutes: i64 = parse_digits(parts[1])?;
+    let seconds: i64 = match parts.get(2) {
+        Some(seconds) => parse_digits(seconds)?,
+        None => 0,
+    };
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return Err(());
+    }
+
+    Ok(hours * 3600 + minutes * 60 + seconds)
+}
+
+fn parse_digits(value: &str) -> Result<i64, ()> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(());
+    }
+    value.parse().map_err(|_| ())
+}
+
+// Howard Hinnant's days_from_civil / civil_from_days algorithms
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year: i64 = if month <= 2 { year - 1 } else { year };
+    let era: i64 = year.div_euclid(400);
+    let year_of_era: i64 = year - era * 400;
+    let day_of_year: i64 = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era: i64 = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days: i64 = days + 719_468;
+    let era: i64 = days.div_euclid(146_097);
+    let day_of_era: i64 = days - era * 146_097;
+    let year_of_era: i64 = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month: i64 = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year: i64 = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("0"), Ok(0));
+        assert_eq!(parse_timestamp(" 1790000000 "), Ok(1_790_000_000));
+        assert_eq!(parse_timestamp("1970-01-02"), Ok(SECONDS_PER_DAY));
+        assert_eq!(parse_timestamp("2026-09-30T12:30:15Z"), Ok(1_790_771_415));
+        assert_eq!(parse_timestamp("2026-09-30 12:30"), Ok(1_790_771_400));
+    }
+
+    #[test]
+    fn test_parse_timestamp_invalid() {
+        assert!(parse_timestamp("").is_err());
+        assert!(parse_timestamp("abc").is_err());
+        assert!(parse_timestamp("2026-02-30").is_err());
+        assert!(parse_timestamp("2026-09-30T24:00").is_err());
+        assert!(parse_timestamp("2026-09-30X12:00").is_err());
+        assert!(parse_timestamp("2026-09-30T1:00").is_err());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01"), Ok(0));
+        assert_eq!(parse_date("2000-03-01"), Ok(11_017));
+        assert_eq!(parse_date("1969-12-31"), Ok(-1));
+        assert_eq!(parse_date("2024-02-29"), Ok(19_782));
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("2023-1-01").is_err());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1_790_771_415), "2026-09-30T12:30:15Z");
+        assert_eq!(format_timestamp(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_day_of() {
+        assert_eq!(day_of(0), 0);
+        assert_eq!(day_of(SECONDS_PER_DAY - 1), 0);
+        assert_eq!(day_of(-1), -1);
+    }
+}
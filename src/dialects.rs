@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use csv::{ReaderBuilder, Reader, StringRecord};
+use crate::columns::parse_column_alias;
+
+// How a transactions file is written. A UTF-8 byte order mark at the start of the
+// file is always skipped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dialect {
+    pub delimiter: u8,
+    // `None` reads quote characters as part of the field
+    pub quote: Option<u8>,
+    // Lines starting with this character are skipped
+    pub comment: Option<u8>,
+    pub has_headers: bool,
+    // Amounts use a comma as the decimal point, e.g. `100,50`
+    pub decimal_comma: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect {
+            delimiter: b',',
+            quote: Some(b'"'),
+            comment: None,
+            has_headers: true,
+            decimal_comma: false,
+        }
+    }
+}
+
+impl Dialect {
+    pub fn reader_builder(&self) -> ReaderBuilder {
+        let mut builder: ReaderBuilder = ReaderBuilder::new();
+        builder
+            .flexible(true)
+            .delimiter(self.delimiter)
+            .quoting(self.quote.is_some())
+            .comment(self.comment)
+            .has_headers(self.has_headers);
+        if let Some(quote) = self.quote {
+            builder.quote(quote);
+        }
+        builder
+    }
+}
+
+// A single ASCII character, or one of `comma`, `semicolon`, `tab` and `pipe`
+pub fn parse_delimiter(value: &str) -> Result<u8, ()> {
+    match value {
+        "comma" => Ok(b','),
+        "semicolon" => Ok(b';'),
+        "tab" | "\\t" => Ok(b'\t'),
+        "pipe" => Ok(b'|'),
+        _ => parse_character(value),
+    }
+}
+
+// A single ASCII character, or `none` to switch quoting off
+pub fn parse_quote(value: &str) -> Result<Option<u8>, ()> {
+    match value {
+        "none" => Ok(None),
+        _ => parse_character(value).map(Some),
+    }
+}
+
+pub fn parse_character(value: &str) -> Result<u8, ()> {
+    match value.as_bytes() {
+        [character] if character.is_ascii() && *character != b'\n' && *character != b'\r' => Ok(*character),
+        _ => Err(()),
+    }
+}
+
+// The settings kept for one partner
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub dialect: Dialect,
+    pub column_aliases: HashMap<String, String>,
+}
+
+// Profiles are read by header name from a file with a `name` column and optional
+// `delimiter`, `quote`, `comment`, `headers`, `decimal_comma` and `aliases` columns, one
+// row per partner. Empty cells keep the default. Aliases are written as space-separated
+// `<header>=<column>` pairs.
+pub fn load_profile(file_path: &str, name: &str) -> Result<Profile, String> {
+    let mut rdr: Reader<std::fs::File> = ReaderBuilder::new()
+        .from_path(file_path)
+        .map_err(|err| format!("Unable to read profiles file {}: {}", file_path, err))?;
+    let headers: StringRecord = rdr.headers()
+        .map_err(|err| format!("Unable to read profiles file {}: {}", file_path, err))?
+        .clone();
+    let column = |column_name: &str| headers.iter().position(|header| header.trim() == column_name);
+    let name_column: usize = column("name")
+        .ok_or(format!("Profiles file {} has no name column", file_path))?;
+    let columns: [Option<usize>; 6] = [
+        column("delimiter"),
+        column("quote"),
+        column("comment"),
+        column("headers"),
+        column("decimal_comma"),
+        column("aliases"),
+    ];
+
+    for result in rdr.records() {
+        let record: StringRecord = match result {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+        if record.get(name_column).map(|value| value.trim()) != Some(name) {
+            continue;
+        }
+        return parse_profile(&record, &columns)
+            .map_err(|_| format!("Profile {} in {} has an invalid setting", name, file_path));
+    }
+
+    Err(format!("Profiles file {} has no profile {}", file_path, name))
+}
+
+fn parse_profile(record: &StringRecord, columns: &[Option<usize>; 6]) -> Result<Profile, ()> {
+    let setting = |index: usize| columns[index]
+        .and_then(|column| record.get(column))
+        .filter(|value| !value.is_empty());
+    let mut dialect: Dialect = Dialect::default();
+    if let Some(value) = setting(0) {
+        dialect.delimiter = parse_delimiter(value)?;
+    }
+    if let Some(value) = setting(1) {
+        dialect.quote = parse_quote(value)?;
+    }
+    if let Some(value) = setting(2) {
+        dialect.comment = Some(parse_character(value)?);
+    }
+    if let Some(value) = setting(3) {
+        dialect.has_headers = value.trim().parse().map_err(|_| ())?;
+    }
+    if let Some(value) = setting(4) {
+        dialect.decimal_comma = value.trim().parse().map_err(|_| ())?;
+    }
+    // A comma could not be both the delimiter and the decimal point
+    if dialect.decimal_comma && dialect.delimiter == b',' {
+        return Err(());
+    }
+    let column_aliases: HashMap<String, String> = match setting(5) {
+        Some(value) => value.split_whitespace().map(parse_column_alias).collect::<Result<_, ()>>()?,
+        None => HashMap::new(),
+    };
+    Ok(Profile { dialect, column_aliases })
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    fn write_temp_file(name: &str, contents: &str) -> String {
+        let path: std::path::PathBuf = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse_settings() {
+        assert_eq!(parse_delimiter("semicolon"), Ok(b';'));
+        assert_eq!(parse_delimiter("tab"), Ok(b'\t'));
+        assert_eq!(parse_delimiter("|"), Ok(b'|'));
+        assert!(parse_delimiter(";;").is_err());
+        assert_eq!(parse_quote("none"), Ok(None));
+        assert_eq!(parse_quote("'"), Ok(Some(b'\'')));
+        assert!(parse_character("").is_err());
+        assert!(parse_character("é").is_err());
+    }
+
+    #[test]
+    fn test_load_profile() {
+        let file_path: String = write_temp_file(
+            "rust_ledger_test_load_profile.csv",
+            "name,delimiter,quote,comment,headers,decimal_comma,aliases\n\
+            nordbank,semicolon,,#,,true,Konto=client Reference=tx\n\
+            pipes,pipe,none,,false,,\n\
+            broken,;;,,,,,\n\
+            commas,,,,,true,\n\
+            unknown_alias,,,,,,konto=account\n",
+        );
+
+        assert_eq!(load_profile(&file_path, "nordbank"), Ok(Profile {
+            dialect: Dialect {
+                delimiter: b';',
+                comment: Some(b'#'),
+                decimal_comma: true,
+                ..Default::default()
+            },
+            column_aliases: HashMap::from([
+                ("konto".to_string(), "client".to_string()),
+                ("reference".to_string(), "tx".to_string()),
+            ]),
+        }));
+        assert_eq!(load_profile(&file_path, "pipes").map(|profile| profile.dialect), Ok(Dialect {
+            delimiter: b'|',
+            quote: None,
+            has_headers: false,
+            ..Default::default()
+        }));
+        assert!(load_profile(&file_path, "broken").is_err());
+        assert!(load_profile(&file_path, "commas").is_err());
+        assert!(load_profile(&file_path, "unknown_alias").is_err());
+        assert!(load_profile(&file_path, "missing").is_err());
+    }
+}
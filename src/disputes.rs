@@ -0,0 +1,245 @@
+use crate::dates::format_timestamp;
+use crate::utils::{Account, Transaction};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisputeStatus {
+    Open,
+    Resolved,
+    ChargedBack,
+    Reversed,
+}
+
+impl DisputeStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisputeStatus::Open => "open",
+            DisputeStatus::Resolved => "resolved",
+            DisputeStatus::ChargedBack => "charged_back",
+            DisputeStatus::Reversed => "reversed",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisputeEventKind {
+    Opened,
+    // A further part of a deposit that is already under dispute
+    Extended,
+    Reopened,
+    Resolved,
+    ChargedBack,
+    ChargebackReversed,
+}
+
+impl DisputeEventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisputeEventKind::Opened => "opened",
+            DisputeEventKind::Extended => "extended",
+            DisputeEventKind::Reopened => "reopened",
+            DisputeEventKind::Resolved => "resolved",
+            DisputeEventKind::ChargedBack => "charged_back",
+            DisputeEventKind::ChargebackReversed => "chargeback_reversed",
+        }
+    }
+}
+
+// One dispute, resolve, chargeback or reversal row, with the state of the dispute after it
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisputeEvent {
+    pub kind: DisputeEventKind,
+    pub row: u64,
+    pub timestamp: Option<i64>,
+    pub amount: f32,
+    pub disputed_amount: f32,
+    pub status: DisputeStatus,
+}
+
+// The lifecycle of the disputes raised against one deposit
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisputeRecord {
+    pub id: u64,
+    pub client_id: u16,
+    pub tx: u32,
+    pub currency: Option<String>,
+    pub status: DisputeStatus,
+    pub redisputes: u32,
+    pub events: Vec<DisputeEvent>,
+}
+
+impl DisputeRecord {
+    pub fn opened_at(&self) -> Option<i64> {
+        self.events.first().and_then(|event| event.timestamp)
+    }
+
+    pub fn updated_at(&self) -> Option<i64> {
+        self.events.last().and_then(|event| event.timestamp)
+    }
+}
+
+// Adds an applied dispute, resolve, chargeback or chargeback reversal row to the
+// record of its deposit, opening a record with the next id on the first dispute
+pub fn record_dispute_event(account: &mut Account, transaction: &Transaction, next_dispute_id: &mut u64) {
+    let Some(deposit) = account.transactions.iter()
+        .find(|deposit| deposit.tx == transaction.tx && deposit.tx_type == "deposit") else {
+        return;
+    };
+    let record: &mut DisputeRecord = account.disputes.entry(transaction.tx).or_insert_with(|| {
+        *next_dispute_id += 1;
+        DisputeRecord {
+            id: *next_dispute_id,
+            client_id: transaction.client_id,
+            tx: transaction.tx,
+            currency: transaction.currency.clone(),
+            status: DisputeStatus::Open,
+            redisputes: 0,
+            events: vec![],
+        }
+    });
+    let kind: DisputeEventKind = match transaction.tx_type.as_str() {
+        "dispute" if record.events.is_empty() => DisputeEventKind::Opened,
+        "dispute" if record.status == DisputeStatus::Open => DisputeEventKind::Extended,
+        "dispute" => DisputeEventKind::Reopened,
+        "resolve" => DisputeEventKind::Resolved,
+        "chargeback" => DisputeEventKind::ChargedBack,
+        _ => DisputeEventKind::ChargebackReversed,
+    };
+    if kind == DisputeEventKind::Reopened {
+        record.redisputes += 1;
+    }
+    record.status = match kind {
+        _ if deposit.disputed => DisputeStatus::Open,
+        DisputeEventKind::ChargedBack => DisputeStatus::ChargedBack,
+        _ if deposit.charged_back => DisputeStatus::ChargedBack,
+        DisputeEventKind::ChargebackReversed => DisputeStatus::Reversed,
+        _ => DisputeStatus::Resolved,
+    };
+    record.events.push(DisputeEvent {
+        kind,
+        row: transaction.row,
+        timestamp: transaction.timestamp,
+        amount: transaction.amount.unwrap_or(0.0),
+        disputed_amount: deposit.disputed_amount,
+        status: record.status,
+    });
+}
+
+// Whether a dispute row would reopen a closed dispute more often than allowed
+pub fn is_redispute_limit_exceeded(account: &Account, transaction: &Transaction, max_redisputes: Option<u32>) -> bool {
+    let Some(max_redisputes) = max_redisputes else {
+        return false;
+    };
+    account.disputes.get(&transaction.tx).is_some_and(|record| {
+        record.status != DisputeStatus::Open && record.redisputes >= max_redisputes
+    })
+}
+
+// Lists every event of every dispute, ordered by dispute id
+pub fn output_disputes(records: &[&DisputeRecord]) {
+    println!("dispute,client,tx,event,row,timestamp,amount,disputed,currency,status");
+    for record in records {
+        for event in record.events.iter() {
+            println!(
+                "{},{},{},{},{},{},{:.4},{:.4},{},{}",
+                record.id,
+                record.client_id,
+                record.tx,
+                event.kind.name(),
+                event.row,
+                event.timestamp.map(format_timestamp).unwrap_or_default(),
+                event.amount,
+                event.disputed_amount,
+                record.currency.as_deref().unwrap_or_default(),
+                event.status.name()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    fn dispute_row(tx_type: &str, amount: f32, row: u64) -> Transaction {
+        Transaction {
+            tx_type: tx_type.to_string(),
+            client_id: 1,
+            tx: 7,
+            amount: Some(amount),
+            row,
+            timestamp: Some(row as i64 * 60),
+            ..Default::default()
+        }
+    }
+
+    fn account_with_deposit(disputed_amount: f32, charged_back_amount: f32) -> Account {
+        Account {
+            transactions: vec![Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 7,
+                amount: Some(100.0),
+                disputed: disputed_amount > 0.0,
+                disputed_amount,
+                charged_back: charged_back_amount > 0.0,
+                charged_back_amount,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_dispute_lifecycle() {
+        let mut next_dispute_id: u64 = 4;
+        let mut account: Account = account_with_deposit(40.0, 0.0);
+        record_dispute_event(&mut account, &dispute_row("dispute", 40.0, 2), &mut next_dispute_id);
+        account.transactions[0].disputed_amount = 100.0;
+        record_dispute_event(&mut account, &dispute_row("dispute", 60.0, 3), &mut next_dispute_id);
+        account.transactions[0].disputed = false;
+        account.transactions[0].disputed_amount = 0.0;
+        record_dispute_event(&mut account, &dispute_row("resolve", 100.0, 4), &mut next_dispute_id);
+        account.transactions[0].disputed = true;
+        account.transactions[0].disputed_amount = 100.0;
+        record_dispute_event(&mut account, &dispute_row("dispute", 100.0, 5), &mut next_dispute_id);
+        account.transactions[0].disputed = false;
+        account.transactions[0].disputed_amount = 0.0;
+        account.transactions[0].charged_back = true;
+        record_dispute_event(&mut account, &dispute_row("chargeback", 100.0, 6), &mut next_dispute_id);
+
+        let record: &DisputeRecord = account.disputes.get(&7).unwrap();
+        let kinds: Vec<(DisputeEventKind, DisputeStatus)> = record.events.iter().map(|event| (event.kind, event.status)).collect();
+
+        assert_eq!(record.id, 5);
+        assert_eq!(next_dispute_id, 5);
+        assert_eq!(record.redisputes, 1);
+        assert_eq!(record.status, DisputeStatus::ChargedBack);
+        assert_eq!(record.opened_at(), Some(120));
+        assert_eq!(record.updated_at(), Some(360));
+        assert_eq!(kinds, vec![
+            (DisputeEventKind::Opened, DisputeStatus::Open),
+            (DisputeEventKind::Extended, DisputeStatus::Open),
+            (DisputeEventKind::Resolved, DisputeStatus::Resolved),
+            (DisputeEventKind::Reopened, DisputeStatus::Open),
+            (DisputeEventKind::ChargedBack, DisputeStatus::ChargedBack),
+        ]);
+    }
+
+    #[test]
+    fn test_is_redispute_limit_exceeded() {
+        let mut next_dispute_id: u64 = 0;
+        let mut account: Account = account_with_deposit(100.0, 0.0);
+        record_dispute_event(&mut account, &dispute_row("dispute", 100.0, 2), &mut next_dispute_id);
+        let redispute: Transaction = dispute_row("dispute", 100.0, 4);
+
+        assert!(!is_redispute_limit_exceeded(&account, &redispute, Some(0)));
+
+        account.transactions[0].disputed = false;
+        account.transactions[0].disputed_amount = 0.0;
+        record_dispute_event(&mut account, &dispute_row("resolve", 100.0, 3), &mut next_dispute_id);
+
+        assert!(is_redispute_limit_exceeded(&account, &redispute, Some(0)));
+        assert!(!is_redispute_limit_exceeded(&account, &redispute, Some(1)));
+        assert!(!is_redispute_limit_exceeded(&account, &redispute, None));
+    }
+}
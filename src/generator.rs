@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use crate::utils::Snapshot;
+
+// Each rate is the share of rows of that kind. Disputes need a settled deposit and
+// resolves and chargebacks an open dispute; rows that cannot be given one become
+// deposits. The remaining rows are deposits.
+pub struct GeneratorOptions {
+    pub clients: u16,
+    pub rows: u64,
+    pub seed: u64,
+    pub withdrawal_rate: f64,
+    pub dispute_rate: f64,
+    pub resolve_rate: f64,
+    pub chargeback_rate: f64,
+    pub malformed_rate: f64,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> GeneratorOptions {
+        GeneratorOptions {
+            clients: 100,
+            rows: 10_000,
+            seed: 1,
+            withdrawal_rate: 0.3,
+            dispute_rate: 0.02,
+            resolve_rate: 0.01,
+            chargeback_rate: 0.005,
+            malformed_rate: 0.001,
+        }
+    }
+}
+
+// SplitMix64, so that a seed gives the same file on every platform
+struct Random(u64);
+
+impl Random {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value: u64 = self.0;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    fn chance(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DepositState {
+    Settled,
+    Disputed,
+    ChargedBack,
+}
+
+#[derive(Default)]
+struct ExpectedAccount {
+    available: f32,
+    held: f32,
+    locked: bool,
+    chargebacks: u32,
+    deposit_ids: Vec<u32>,
+    deposits: HashMap<u32, (f32, DepositState)>,
+}
+
+// Writes a transactions file in the `type,client,tx,amount` layout and returns the
+// balances the engine should arrive at. The expected balances follow the engine's
+// rules with the same f32 arithmetic, so they match the summary exactly.
+pub fn generate<W: Write>(options: &GeneratorOptions, writer: &mut W) -> io::Result<BTreeMap<u16, Snapshot>> {
+    let mut random: Random = Random(options.seed);
+    let mut accounts: BTreeMap<u16, ExpectedAccount> = BTreeMap::new();
+    let mut open_disputes: Vec<(u16, u32)> = vec![];
+    let mut next_tx: u32 = 1;
+    let clients: u64 = u64::from(options.clients.max(1));
+
+    let resolve_limit: f64 = options.dispute_rate + options.resolve_rate;
+    let chargeback_limit: f64 = resolve_limit + options.chargeback_rate;
+    let withdrawal_limit: f64 = chargeback_limit + options.withdrawal_rate;
+
+    writeln!(writer, "type,client,tx,amount")?;
+    for _ in 0..options.rows {
+        if random.chance() < options.malformed_rate {
+            writeln!(writer, "{}", malformed_row(&mut random, next_tx))?;
+            continue;
+        }
+        let roll: f64 = random.chance();
+        let row: String = if roll >= options.dispute_rate && roll < chargeback_limit && !open_disputes.is_empty() {
+            let (client_id, tx) = open_disputes.swap_remove(random.below(open_disputes.len() as u64) as usize);
+            let account: &mut ExpectedAccount = accounts.get_mut(&client_id).unwrap();
+            settle_dispute(account, client_id, tx, roll < resolve_limit)
+        } else {
+            let client_id: u16 = random.below(clients) as u16 + 1;
+            let account: &mut ExpectedAccount = accounts.entry(client_id).or_default();
+            let settled: Option<u32> = if roll < options.dispute_rate {
+                pick_settled_deposit(&mut random, account)
+            } else {
+                None
+            };
+            if let Some(tx) = settled {
+                if dispute(account, tx) {
+                    open_disputes.push((client_id, tx));
+                }
+                format!("dispute,{},{},", client_id, tx)
+            } else if roll >= chargeback_limit && roll < withdrawal_limit {
+                // Some withdrawals are larger than the balance and will be rejected
+                let limit: u64 = (account.available.max(0.0) as f64 * 120.0) as u64 + 100;
+                let text: String = format!("{:.2}", (random.below(limit) + 1) as f64 / 100.0);
+                let amount: f32 = text.parse().unwrap();
+                if !account.locked && account.available >= amount {
+                    account.available -= amount;
+                }
+                format!("withdrawal,{},{},{}", client_id, take_tx(&mut next_tx), text)
+            } else {
+                let text: String = format!("{:.2}", (random.below(500_000) + 100) as f64 / 100.0);
+                let amount: f32 = text.parse().unwrap();
+                let tx: u32 = take_tx(&mut next_tx);
+                if !account.locked {
+                    account.available += amount;
+                    account.deposit_ids.push(tx);
+                    account.deposits.insert(tx, (amount, DepositState::Settled));
+                }
+                format!("deposit,{},{},{}", client_id, tx, text)
+            }
+        };
+        writeln!(writer, "{}", pad(&mut random, &row))?;
+    }
+
+    Ok(accounts.into_iter()
+        .map(|(client_id, account)| (client_id, Snapshot {
+            available: account.available,
+            held: account.held,
+            locked: account.locked,
+            chargebacks: account.chargebacks,
+            flagged: false,
+        }))
+        .collect())
+}
+
+// Returns whether the dispute was accepted
+fn dispute(account: &mut ExpectedAccount, tx: u32) -> bool {
+    let amount: f32 = deposit_amount(account, tx);
+    if account.locked || amount > account.available {
+        return false;
+    }
+    account.available -= amount;
+    account.held += amount;
+    set_deposit_state(account, tx, DepositState::Disputed);
+    true
+}
+
+fn settle_dispute(account: &mut ExpectedAccount, client_id: u16, tx: u32, is_resolve: bool) -> String {
+    let row: String = format!("{},{},{},", if is_resolve { "resolve" } else { "chargeback" }, client_id, tx);
+    if account.locked {
+        return row;
+    }
+    let amount: f32 = deposit_amount(account, tx);
+    account.held -= amount;
+    if is_resolve {
+        account.available += amount;
+        set_deposit_state(account, tx, DepositState::Settled);
+    } else {
+        account.locked = true;
+        account.chargebacks += 1;
+        set_deposit_state(account, tx, DepositState::ChargedBack);
+    }
+    row
+}
+
+// Tries a few deposits at random rather than scanning the client's history, so that
+// large files generate in linear time
+fn pick_settled_deposit(random: &mut Random, account: &ExpectedAccount) -> Option<u32> {
+    if account.deposit_ids.is_empty() {
+        return None;
+    }
+    for _ in 0..8 {
+        let tx: u32 = account.deposit_ids[random.below(account.deposit_ids.len() as u64) as usize];
+        if account.deposits.get(&tx).is_some_and(|deposit| deposit.1 == DepositState::Settled) {
+            return Some(tx);
+        }
+    }
+    None
+}
+
+fn deposit_amount(account: &ExpectedAccount, tx: u32) -> f32 {
+    account.deposits.get(&tx).map_or(0.0, |deposit| deposit.0)
+}
+
+fn take_tx(next_tx: &mut u32) -> u32 {
+    let tx: u32 = *next_tx;
+    *next_tx = next_tx.wrapping_add(1);
+    tx
+}
+
+fn set_deposit_state(account: &mut ExpectedAccount, tx: u32, state: DepositState) {
+    if let Some(deposit) = account.deposits.get_mut(&tx) {
+        deposit.1 = state;
+    }
+}
+
+// Partner files are not always tidy, so one row in ten has padded fields
+fn pad(random: &mut Random, row: &str) -> String {
+    if random.below(10) != 0 {
+        return row.to_string();
+    }
+    row.split(',').map(|field| format!(" {} ", field)).collect::<Vec<String>>().join(",")
+}
+
+// Rows that fail to parse and are skipped by the engine
+fn malformed_row(random: &mut Random, tx: u32) -> String {
+    match random.below(4) {
+        0 => format!("deposit,abc,{},10.00", tx),
+        1 => format!("deposit,1,{},-10.00", tx),
+        2 => "withdrawal,1,xyz,5.00".to_string(),
+        _ => format!("deposit,1,{},12.3.4", tx),
+    }
+}
+
+// The expected balances in the summary layout, ordered by client
+pub fn output_expected_balances<W: Write>(balances: &BTreeMap<u16, Snapshot>, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "client,available,held,total,locked,overdraft,currency,chargebacks,flagged")?;
+    for (client_id, balance) in balances.iter() {
+        let overdraft: f32 = if balance.available < 0.0 { -balance.available } else { 0.0 };
+        writeln!(
+            writer,
+            "{},{:.4},{:.4},{:.4},{},{:.4},,{},{}",
+            client_id,
+            balance.available,
+            balance.held,
+            balance.available + balance.held,
+            balance.locked,
+            overdraft,
+            balance.chargebacks,
+            balance.flagged
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    fn generate_text(options: &GeneratorOptions) -> String {
+        let mut output: Vec<u8> = vec![];
+        generate(options, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_generate_is_deterministic() {
+        let options: GeneratorOptions = GeneratorOptions {
+            rows: 200,
+            ..Default::default()
+        };
+
+        assert_eq!(generate_text(&options), generate_text(&options));
+        assert_ne!(generate_text(&options), generate_text(&GeneratorOptions { seed: 2, ..options }));
+    }
+
+    #[test]
+    fn test_generate_row_count_and_layout() {
+        let text: String = generate_text(&GeneratorOptions {
+            clients: 3,
+            rows: 500,
+            malformed_rate: 0.0,
+            ..Default::default()
+        });
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "type,client,tx,amount");
+        assert_eq!(lines.len(), 501);
+        assert!(lines[1..].iter().all(|line| line.split(',').count() == 4));
+        assert!(lines[1..].iter().any(|line| line.starts_with("withdrawal")));
+    }
+
+    #[test]
+    fn test_output_expected_balances() {
+        let balances: BTreeMap<u16, Snapshot> = BTreeMap::from([
+            (2, Snapshot { available: -5.0, held: 1.0, locked: true, chargebacks: 1, flagged: false }),
+            (1, Snapshot { available: 10.0, held: 0.0, locked: false, ..Default::default() }),
+        ]);
+        let mut output: Vec<u8> = vec![];
+        output_expected_balances(&balances, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "client,available,held,total,locked,overdraft,currency,chargebacks,flagged\n\
+            1,10.0000,0.0000,10.0000,false,0.0000,,0,false\n\
+            2,-5.0000,1.0000,-4.0000,true,5.0000,,1,false\n"
+        );
+    }
+}
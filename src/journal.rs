@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use crate::utils::Transaction;
+
+// The chart of accounts. Client accounts are liabilities owed to each client, while
+// settlement holds the funds that have moved in and out of the business.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LedgerAccount {
+    ClientAvailable(u16),
+    ClientHeld(u16),
+    Settlement,
+    ChargebackLoss,
+    Fees,
+}
+
+impl LedgerAccount {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LedgerAccount::ClientAvailable(_) => "client_available",
+            LedgerAccount::ClientHeld(_) => "client_held",
+            LedgerAccount::Settlement => "settlement",
+            LedgerAccount::ChargebackLoss => "chargeback_loss",
+            LedgerAccount::Fees => "fees",
+        }
+    }
+
+    pub fn client_id(&self) -> Option<u16> {
+        match self {
+            LedgerAccount::ClientAvailable(client_id) | LedgerAccount::ClientHeld(client_id) => Some(*client_id),
+            _ => None,
+        }
+    }
+}
+
+// Debits are positive amounts and credits negative ones
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalLine {
+    pub row: u64,
+    pub tx: u32,
+    pub tx_type: String,
+    pub account: LedgerAccount,
+    pub currency: Option<String>,
+    pub amount: f32,
+}
+
+#[derive(Default)]
+pub struct Journal {
+    pub lines: Vec<JournalLine>,
+    // Set in bounded-memory mode, where lines are folded into these balances as they
+    // are posted instead of being kept
+    pub running_balances: Option<BTreeMap<(LedgerAccount, Option<String>), f32>>,
+}
+
+impl Journal {
+    pub fn running() -> Journal {
+        Journal {
+            lines: vec![],
+            running_balances: Some(BTreeMap::new()),
+        }
+    }
+
+    // Posts a balanced pair of lines moving `amount` from the credited account to
+    // the debited one
+    pub fn post(&mut self, transaction: &Transaction, currency: &Option<String>, debit: LedgerAccount, credit: LedgerAccount, amount: f32) {
+        for (account, amount) in [(debit, amount), (credit, -amount)] {
+            if let Some(balances) = &mut self.running_balances {
+                *balances.entry((account, currency.clone())).or_default() += amount;
+                continue;
+            }
+            self.lines.push(JournalLine {
+                row: transaction.row,
+                tx: transaction.tx,
+                tx_type: transaction.tx_type.clone(),
+                account,
+                currency: currency.clone(),
+                amount,
+            });
+        }
+    }
+
+    // The net balance of every account and currency. Debits are positive, so client
+    // accounts in credit show as negative balances.
+    pub fn trial_balance(&self) -> BTreeMap<(LedgerAccount, Option<String>), f32> {
+        if let Some(balances) = &self.running_balances {
+            return balances.clone();
+        }
+        let mut balances: BTreeMap<(LedgerAccount, Option<String>), f32> = BTreeMap::new();
+        for line in self.lines.iter() {
+            *balances.entry((line.account.clone(), line.currency.clone())).or_default() += line.amount;
+        }
+        balances
+    }
+}
+
+pub fn output_journal(journal: &Journal) {
+    println!("row,tx,type,account,client,currency,debit,credit");
+    for line in journal.lines.iter() {
+        let (debit, credit) = split_amount(line.amount);
+        println!(
+            "{},{},{},{},{},{},{},{}",
+            line.row,
+            line.tx,
+            line.tx_type,
+            line.account.name(),
+            line.account.client_id().map(|client_id| client_id.to_string()).unwrap_or_default(),
+            line.currency.as_deref().unwrap_or_default(),
+            debit,
+            credit
+        );
+    }
+}
+
+// Lists the net debit or credit of every account, followed by a total row for each
+// currency. The debit and credit totals of a currency are always equal.
+pub fn output_trial_balance(balances: &BTreeMap<(LedgerAccount, Option<String>), f32>) {
+    println!("account,client,currency,debit,credit");
+    let mut totals: BTreeMap<Option<String>, (f32, f32)> = BTreeMap::new();
+    for ((account, currency), balance) in balances.iter() {
+        let (debit, credit) = split_amount(*balance);
+        let total: &mut (f32, f32) = totals.entry(currency.clone()).or_default();
+        total.0 += balance.max(0.0);
+        total.1 += (-balance).max(0.0);
+        println!(
+            "{},{},{},{},{}",
+            account.name(),
+            account.client_id().map(|client_id| client_id.to_string()).unwrap_or_default(),
+            currency.as_deref().unwrap_or_default(),
+            debit,
+            credit
+        );
+    }
+    for (currency, (debit, credit)) in totals.iter() {
+        println!("total,,{},{:.4},{:.4}", currency.as_deref().unwrap_or_default(), debit, credit);
+    }
+}
+
+fn split_amount(amount: f32) -> (String, String) {
+    if amount >= 0.0 {
+        (format!("{:.4}", amount), String::new())
+    } else {
+        (String::new(), format!("{:.4}", -amount))
+    }
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_post_is_balanced() {
+        let mut journal: Journal = Journal::default();
+        let transaction: Transaction = Transaction {
+            tx_type: "deposit".to_string(),
+            client_id: 1,
+            tx: 1,
+            row: 3,
+            ..Default::default()
+        };
+
+        journal.post(&transaction, &None, LedgerAccount::Settlement, LedgerAccount::ClientAvailable(1), 25.0);
+
+        assert_eq!(journal.lines.len(), 2);
+        assert_eq!(journal.lines[0].account, LedgerAccount::Settlement);
+        assert_eq!(journal.lines[0].amount, 25.0);
+        assert_eq!(journal.lines[1].account, LedgerAccount::ClientAvailable(1));
+        assert_eq!(journal.lines[1].amount, -25.0);
+        assert_eq!(journal.lines[1].row, 3);
+    }
+
+    #[test]
+    fn test_trial_balance() {
+        let mut journal: Journal = Journal::default();
+        let transaction: Transaction = Transaction::default();
+        let euro: Option<String> = Some("EUR".to_string());
+
+        journal.post(&transaction, &euro, LedgerAccount::Settlement, LedgerAccount::ClientAvailable(1), 100.0);
+        journal.post(&transaction, &euro, LedgerAccount::ClientAvailable(1), LedgerAccount::ClientHeld(1), 40.0);
+        journal.post(&transaction, &None, LedgerAccount::Settlement, LedgerAccount::ClientAvailable(2), 5.0);
+        let balances: BTreeMap<(LedgerAccount, Option<String>), f32> = journal.trial_balance();
+
+        assert_eq!(balances.get(&(LedgerAccount::Settlement, euro.clone())), Some(&100.0));
+        assert_eq!(balances.get(&(LedgerAccount::ClientAvailable(1), euro.clone())), Some(&-60.0));
+        assert_eq!(balances.get(&(LedgerAccount::ClientHeld(1), euro.clone())), Some(&-40.0));
+        assert_eq!(balances.values().sum::<f32>(), 0.0);
+    }
+
+    #[test]
+    fn test_running_journal_keeps_balances_only() {
+        let mut journal: Journal = Journal::running();
+        let mut full_journal: Journal = Journal::default();
+        let transaction: Transaction = Transaction::default();
+
+        for journal in [&mut journal, &mut full_journal] {
+            journal.post(&transaction, &None, LedgerAccount::Settlement, LedgerAccount::ClientAvailable(1), 100.0);
+            journal.post(&transaction, &None, LedgerAccount::ClientAvailable(1), LedgerAccount::ClientHeld(1), 40.0);
+        }
+
+        assert!(journal.lines.is_empty());
+        assert_eq!(journal.trial_balance(), full_journal.trial_balance());
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use csv::StringRecord;
+use serde_json::Value;
+use crate::columns::{COLUMNS, ColumnMap, column_for_header};
+use crate::dialects::Dialect;
+use crate::utils::{Transaction, process_row};
+
+// Keys that every line must have. The amount may be left out, as on dispute rows.
+const REQUIRED_KEYS: usize = 3;
+
+// Reads one line of newline-delimited JSON such as
+// `{"type": "deposit", "client": 1, "tx": 1, "amount": 1.5}`. Keys are matched like
+// CSV headers, including aliases, and unknown keys are ignored. Values may be strings
+// or numbers and are then checked exactly as the fields of a CSV row, so a line is
+// rejected for the same reasons as a row.
+pub fn parse_json_line(line: &[u8], aliases: &HashMap<String, String>) -> Result<Transaction, ()> {
+    let line: &[u8] = line.strip_prefix("\u{feff}".as_bytes()).unwrap_or(line);
+    let Ok(Value::Object(object)) = serde_json::from_slice::<Value>(line) else {
+        return Err(());
+    };
+    let mut fields: Vec<Option<String>> = vec![None; COLUMNS.len()];
+    for (key, value) in object.iter() {
+        let Some(column) = column_for_header(key, aliases) else {
+            continue;
+        };
+        if fields[column].is_some() {
+            continue;
+        }
+        fields[column] = Some(match value {
+            Value::String(text) => text.clone(),
+            Value::Number(number) => number.to_string(),
+            Value::Null => String::new(),
+            _ => return Err(()),
+        });
+    }
+    if fields[..REQUIRED_KEYS].iter().any(|field| field.is_none()) {
+        return Err(());
+    }
+    let record: StringRecord = fields.into_iter().map(Option::unwrap_or_default).collect();
+    process_row(record, &ColumnMap::default(), &Dialect::default())
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Transaction, ()> {
+        parse_json_line(line.as_bytes(), &HashMap::new())
+    }
+
+    #[test]
+    fn test_parse_json_line() {
+        let transaction: Transaction = parse(
+            r#"{"type": "convert", "client": 2, "tx": "7", "amount": 12.5, "timestamp": "2026-09-30", "currency": "eur", "target_currency": "GBP", "source": "bus"}"#,
+        ).unwrap();
+
+        assert_eq!(transaction.tx_type, "convert");
+        assert_eq!(transaction.client_id, 2);
+        assert_eq!(transaction.tx, 7);
+        assert_eq!(transaction.amount, Some(12.5));
+        assert_eq!(transaction.timestamp, Some(1_790_726_400));
+        assert_eq!(transaction.currency, Some("EUR".to_string()));
+        assert_eq!(transaction.target_currency, Some("GBP".to_string()));
+
+        let dispute: Transaction = parse(r#"{"txn": 3, "client_id": 1, "type": "dispute", "amount": null}"#).unwrap();
+        assert_eq!((dispute.tx, dispute.amount), (3, None));
+    }
+
+    #[test]
+    fn test_parse_json_line_malformed() {
+        assert!(parse(r#"{"type": "deposit", "client": 1, "tx": 1, "amount": 1.5"#).is_err());
+        assert!(parse(r#"["deposit", 1, 1, 1.5]"#).is_err());
+        assert!(parse(r#"{"type": "deposit", "client": 1, "amount": 1.5}"#).is_err());
+        assert!(parse(r#"{"type": "deposit", "client": 70000, "tx": 1, "amount": 1.5}"#).is_err());
+        assert!(parse(r#"{"type": "deposit", "client": 1, "tx": 1, "amount": -1.5}"#).is_err());
+        assert!(parse(r#"{"type": "deposit", "client": 1, "tx": 1, "amount": {"value": 1.5}}"#).is_err());
+        assert!(parse("").is_err());
+    }
+}
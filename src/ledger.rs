@@ -1,101 +1,681 @@
-use std::collections::HashMap;
-use csv::{ReaderBuilder, Reader};
-use crate::utils::{Account, Transaction, process_row};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use csv::Reader;
+use crate::config::{Config, InputFormat, LockPolicy};
+use crate::dates::{day_of, format_timestamp, SECONDS_PER_DAY};
+use crate::disputes::{DisputeRecord, DisputeStatus, is_redispute_limit_exceeded, record_dispute_event};
+use crate::journal::{Journal, LedgerAccount};
+use crate::reconcile::{check_invariants, funds_movement};
+use crate::columns::ColumnMap;
+use crate::dialects::Dialect;
+use crate::json_lines::parse_json_line;
+use crate::utils::{Account, Balance, Rejection, Snapshot, Transaction, process_row};
 
-pub fn summarize_accounts(file_path: &str) -> HashMap<u16, Account> {
-    let mut rdr: Reader<std::fs::File> = ReaderBuilder::new().from_path(file_path).unwrap();
-    let mut accounts: HashMap<u16, Account> = HashMap::new();
-    
-    for result in rdr.records() {
-        match result {
-            Ok(record) => {
-                    let (tx_type, client_id, tx, amount) = match process_row(record) {
-                    Ok(data) => data,
-                    Err(_) => continue,
-                };
-                let mut transaction: Transaction = Transaction {
-                    tx_type,
-                    client_id,
-                    tx,
-                    amount,
-                    disputed: false,
-                };
-                process_transaction(&mut transaction, &mut accounts);
+#[derive(Default)]
+pub struct Ledger {
+    pub accounts: HashMap<u16, Account>,
+    pub config: Config,
+    pub row: u64,
+    pub now: Option<i64>,
+    pub pending_authorizations: VecDeque<(u16, u32)>,
+    pub rejections: Vec<(Transaction, Rejection)>,
+    pub journal: Journal,
+    pub violation: Option<String>,
+    pub history_spill: Option<Box<dyn Write>>,
+    pub spill_error: Option<String>,
+    // Why the input could not be read at all, such as a required column missing from the header
+    pub input_error: Option<String>,
+    pub next_dispute_id: u64,
+}
+
+// A history only gets pruned once it has grown past this many entries and doubled
+// since it was last pruned, so that pruning costs a constant amount per row
+const MIN_PRUNED_HISTORY: usize = 16;
+
+impl Ledger {
+    pub fn new(config: Config) -> Ledger {
+        let journal: Journal = match config.retain_rows {
+            Some(_) => Journal::running(),
+            None => Journal::default(),
+        };
+        Ledger {
+            accounts: HashMap::new(),
+            config,
+            row: 0,
+            now: None,
+            pending_authorizations: VecDeque::new(),
+            rejections: vec![],
+            journal,
+            violation: None,
+            history_spill: None,
+            spill_error: None,
+            input_error: None,
+            next_dispute_id: 0,
+        }
+    }
+
+    // Balances at the end of the given day (counted from 1970-01-01) for every client
+    // and currency. Each transaction's own effect is the change from the snapshot before
+    // it, so the effects of the transactions dated on or before that day can be added up
+    // whatever order the rows were applied in. Locking, chargebacks and flagging belong
+    // to the account and are added up the same way across its currencies. Transactions
+    // without a timestamp are not placed in time and are ignored, as are clients with
+    // nothing before the date.
+    pub fn balances_as_of(&self, date: i64) -> HashMap<(u16, Option<String>), Snapshot> {
+        let cutoff: i64 = (date + 1) * SECONDS_PER_DAY;
+        let mut balances: HashMap<(u16, Option<String>), Snapshot> = HashMap::new();
+        for (client_id, account) in self.accounts.iter() {
+            let mut client_balances: HashMap<Option<String>, Snapshot> = HashMap::new();
+            let mut currency_before: HashMap<Option<String>, Snapshot> = HashMap::new();
+            let mut account_before: Snapshot = Snapshot::default();
+            let (mut locks, mut chargebacks, mut flags): (i64, i64, i64) = (0, 0, 0);
+            for transaction in account.transactions.iter() {
+                let after: Snapshot = transaction.snapshot;
+                let before: Snapshot = currency_before.insert(transaction.currency.clone(), after).unwrap_or_default();
+                let previous: Snapshot = std::mem::replace(&mut account_before, after);
+                if transaction.timestamp.is_none_or(|timestamp| timestamp >= cutoff) {
+                    continue;
+                }
+                let balance: &mut Snapshot = client_balances.entry(transaction.currency.clone()).or_default();
+                balance.available += after.available - before.available;
+                balance.held += after.held - before.held;
+                locks += after.locked as i64 - previous.locked as i64;
+                chargebacks += after.chargebacks as i64 - previous.chargebacks as i64;
+                flags += after.flagged as i64 - previous.flagged as i64;
+            }
+            for (currency, balance) in client_balances {
+                balances.insert((*client_id, currency), Snapshot {
+                    locked: locks > 0,
+                    chargebacks: chargebacks.max(0) as u32,
+                    flagged: flags > 0,
+                    ..balance
+                });
             }
+        }
+        balances
+    }
+
+    // Dispute records ordered by id, for one client or for all of them
+    pub fn dispute_records(&self, client_id: Option<u16>) -> Vec<&DisputeRecord> {
+        let mut records: Vec<&DisputeRecord> = self.accounts.iter()
+            .filter(|(id, _)| client_id.is_none_or(|client_id| **id == client_id))
+            .flat_map(|(_, account)| account.disputes.values())
+            .collect();
+        records.sort_by_key(|record| record.id);
+        records
+    }
+}
+
+pub fn summarize_accounts(file_path: &str, config: Config) -> Result<Ledger, String> {
+    let file: File = File::open(file_path)
+        .map_err(|err| format!("Unable to read transactions file {}: {}", file_path, err))?;
+    let mut ledger: Ledger = Ledger::new(config);
+    if let Some(spill_path) = ledger.config.spill_path.clone() {
+        ledger.history_spill = Some(open_history_spill(&spill_path)?);
+    }
+    let ledger: Ledger = summarize_into(file, ledger);
+    if let Some(err) = &ledger.input_error {
+        return Err(format!("Transactions file {} has {}", file_path, err));
+    }
+    match (&ledger.spill_error, &ledger.config.spill_path) {
+        (Some(err), Some(spill_path)) => Err(format!("Unable to write history spill file {}: {}", spill_path, err)),
+        _ => Ok(ledger),
+    }
+}
+
+// Rows that cannot be read or parsed are skipped, so any input produces a ledger. A
+// header without the required columns leaves it empty, with `input_error` set.
+pub fn summarize_reader<R: Read>(reader: R, config: Config) -> Ledger {
+    summarize_into(reader, Ledger::new(config))
+}
+
+pub(crate) fn summarize_into<R: Read>(reader: R, mut ledger: Ledger) -> Ledger {
+    if ledger.config.input_format == InputFormat::JsonLines {
+        let aliases: HashMap<String, String> = ledger.config.column_aliases.clone();
+        // Reading stops at an I/O error, as a line that cannot be read would be tried forever
+        let lines = BufReader::new(reader).split(b'\n').map_while(Result::ok);
+        return summarize_rows(lines.map(move |line| parse_json_line(&line, &aliases)), ledger);
+    }
+    let mut rdr: Reader<R> = ledger.config.dialect.reader_builder().from_reader(reader);
+    let columns: ColumnMap = match rdr.headers() {
+        Ok(headers) if ledger.config.dialect.has_headers && !headers.is_empty() => match ColumnMap::from_headers(headers, &ledger.config.column_aliases) {
+            Ok(columns) => columns,
+            Err(err) => {
+                ledger.input_error = Some(err);
+                return ledger;
+            },
+        },
+        _ => ColumnMap::default(),
+    };
+    let dialect: Dialect = ledger.config.dialect.clone();
+    let rows = rdr.into_records().map(move |result| match result {
+        Ok(record) => process_row(record, &columns, &dialect),
+        Err(_) => Err(()),
+    });
+    summarize_rows(rows, ledger)
+}
+
+// Rows that failed to parse are skipped, whatever the input format
+fn summarize_rows(rows: impl Iterator<Item = Result<Transaction, ()>>, mut ledger: Ledger) -> Ledger {
+    let mut reorder_buffer: BTreeMap<(i64, u64), Transaction> = BTreeMap::new();
+    let mut last_timestamp: i64 = i64::MIN;
+    
+    for (sequence, result) in rows.enumerate() {
+        if ledger.violation.is_some() {
+            break;
+        }
+        let transaction: Transaction = match result {
+            Ok(transaction) => transaction,
             Err(_) => continue,
+        };
+        last_timestamp = transaction.timestamp.unwrap_or(last_timestamp);
+        reorder_buffer.insert((last_timestamp, sequence as u64), transaction);
+        if reorder_buffer.len() > ledger.config.reorder_window
+            && let Some((_, transaction)) = reorder_buffer.pop_first() {
+                record_transaction(transaction, &mut ledger);
+            }
+    }
+    while let Some((_, transaction)) = reorder_buffer.pop_first() {
+        if ledger.violation.is_some() {
+            break;
         }
+        record_transaction(transaction, &mut ledger);
     }
+    if let Some(writer) = &mut ledger.history_spill
+        && let Err(err) = writer.flush() {
+            ledger.spill_error = Some(err.to_string());
+        }
+
+    ledger
+}
 
-    accounts
+// Rejections are not kept in bounded-memory mode, where they would grow with the input
+fn record_transaction(mut transaction: Transaction, ledger: &mut Ledger) {
+    if let Err(rejection) = process_transaction(&mut transaction, ledger)
+        && ledger.config.retain_rows.is_none() {
+            ledger.rejections.push((transaction, rejection));
+        }
 }
 
-pub fn process_transaction(transaction: &mut Transaction, accounts: &mut HashMap<u16, Account>) {
+pub fn process_transaction(transaction: &mut Transaction, ledger: &mut Ledger) -> Result<(), Rejection> {
+
+    ledger.row += 1;
+    transaction.row = ledger.row;
+    if let Some(timestamp) = transaction.timestamp {
+        ledger.now = Some(ledger.now.map_or(timestamp, |now| now.max(timestamp)));
+    }
+    expire_authorizations(ledger);
 
-    accounts.entry(transaction.client_id).or_insert_with(|| Account {
-        available: 0.0,
-        held: 0.0,
-        locked: false,
-        transactions: vec![],
+    let config: &Config = &ledger.config;
+    ledger.accounts.entry(transaction.client_id).or_insert_with(|| Account {
+        limits: config.limits_for(transaction.client_id),
+        ..Default::default()
     });
-    let account: &mut Account = accounts.get_mut(&transaction.client_id).unwrap();
-    if !account.locked {
-        apply_transaction_to_account(transaction, account);
+    let account: &mut Account = ledger.accounts.get_mut(&transaction.client_id).unwrap();
+    // A reversal can be what unlocks the account, so it is let through
+    if account.locked && transaction.tx_type != "chargeback_reversal" {
+        return Err(Rejection::AccountLocked);
+    }
+    if transaction.tx_type == "dispute" {
+        check_dispute_window(transaction, account, &ledger.config, ledger.now)?;
+        if is_redispute_limit_exceeded(account, transaction, ledger.config.max_redisputes) {
+            return Err(Rejection::RedisputeLimitExceeded);
+        }
+    }
+    match transaction.tx_type.as_str() {
+        "convert" => apply_conversion(transaction, account, &ledger.config, &mut ledger.journal)?,
+        _ => apply_transaction_to_account(transaction, account, &ledger.config.lock_policy, &mut ledger.journal)?,
+    }
+    if ["dispute", "resolve", "chargeback", "chargeback_reversal"].contains(&transaction.tx_type.as_str()) {
+        record_dispute_event(account, transaction, &mut ledger.next_dispute_id);
     }
+    if transaction.tx_type == "chargeback_reversal" && ledger.config.unlock_on_reversal {
+        unlock_after_reversal(account, &ledger.config.lock_policy);
+    }
+    if ledger.config.strict
+        && ledger.violation.is_none()
+        && let Err(violation) = check_invariants(account, transaction) {
+            ledger.violation = Some(violation);
+        }
+    // Holds are only queued when they can expire, or the queue would grow with the input
+    let expires: bool = ledger.config.authorization_expiry_rows.is_some() || ledger.config.authorization_expiry_secs.is_some();
+    if transaction.authorized && expires {
+        ledger.pending_authorizations.push_back((transaction.client_id, transaction.tx));
+    }
+    if let Some(retain_rows) = ledger.config.retain_rows
+        && account.transactions.len() >= MIN_PRUNED_HISTORY.max(2 * account.retained_history) {
+            let is_expired = |deposit: &Transaction| {
+                deposit.row.saturating_add(retain_rows) <= ledger.row
+                    || is_dispute_window_expired(deposit, &ledger.config, ledger.row, ledger.now)
+            };
+            prune_history(account, is_expired, &mut ledger.history_spill, &mut ledger.spill_error);
+        }
+    Ok(())
+}
+
+// Keeps only the history entries later rows can refer to: deposits that are under
+// dispute, charged back or not yet expired, the disputes opened on them, and open authorizations.
+// The rest are written to the spill file, if there is one, and their funds are added
+// to the account's pruned totals for reconciliation. Closed dispute records go with
+// their deposits.
+fn prune_history(
+    account: &mut Account,
+    is_expired: impl Fn(&Transaction) -> bool,
+    spill: &mut Option<Box<dyn Write>>,
+    spill_error: &mut Option<String>,
+) {
+    let disputed: HashSet<u32> = account.transactions.iter()
+        .filter(|transaction| transaction.tx_type == "deposit" && transaction.disputed)
+        .map(|transaction| transaction.tx)
+        .collect();
+    let mut retained: Vec<Transaction> = vec![];
+    for transaction in std::mem::take(&mut account.transactions) {
+        let is_retained: bool = match transaction.tx_type.as_str() {
+            "deposit" => transaction.disputed || transaction.charged_back || !is_expired(&transaction),
+            "dispute" => disputed.contains(&transaction.tx),
+            "authorize" => transaction.authorized,
+            _ => false,
+        };
+        if is_retained {
+            retained.push(transaction);
+            continue;
+        }
+        *account.pruned_funds.entry(transaction.currency.clone()).or_default() += funds_movement(&transaction);
+        if let Some(writer) = spill
+            && let Err(err) = write_spilled_transaction(writer, &transaction) {
+                *spill_error = Some(err.to_string());
+                *spill = None;
+            }
+    }
+    let retained_deposits: HashSet<u32> = retained.iter()
+        .filter(|transaction| transaction.tx_type == "deposit")
+        .map(|transaction| transaction.tx)
+        .collect();
+    account.disputes.retain(|tx, record| record.status == DisputeStatus::Open || retained_deposits.contains(tx));
+    account.retained_history = retained.len();
+    account.transactions = retained;
+}
+
+pub fn open_history_spill(file_path: &str) -> Result<Box<dyn Write>, String> {
+    let file: File = File::create(file_path)
+        .map_err(|err| format!("Unable to create history spill file {}: {}", file_path, err))?;
+    let mut writer: BufWriter<File> = BufWriter::new(file);
+    writeln!(writer, "row,client,timestamp,type,tx,amount,currency,available,held")
+        .map_err(|err| format!("Unable to write history spill file {}: {}", file_path, err))?;
+    Ok(Box::new(writer))
+}
+
+fn write_spilled_transaction(writer: &mut Box<dyn Write>, transaction: &Transaction) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "{},{},{},{},{},{},{},{:.4},{:.4}",
+        transaction.row,
+        transaction.client_id,
+        transaction.timestamp.map(format_timestamp).unwrap_or_default(),
+        transaction.tx_type,
+        transaction.tx,
+        transaction.amount.map(|amount| format!("{:.4}", amount)).unwrap_or_default(),
+        transaction.currency.as_deref().unwrap_or_default(),
+        transaction.snapshot.available,
+        transaction.snapshot.held
+    )
+}
+
+// Authorizations are queued in the order they were made, so only the front of the
+// queue needs checking. Captured or released authorizations are dropped as they surface.
+fn expire_authorizations(ledger: &mut Ledger) {
+    if ledger.config.authorization_expiry_rows.is_none() && ledger.config.authorization_expiry_secs.is_none() {
+        return;
+    }
+    while let Some(&(client_id, tx)) = ledger.pending_authorizations.front() {
+        let account: &mut Account = ledger.accounts.get_mut(&client_id).unwrap();
+        let search_transaction: Option<&mut Transaction> = search_matching_authorization(tx, &mut account.transactions);
+        if let Some(authorization) = search_transaction {
+            if !is_authorization_expired(authorization, &ledger.config, ledger.row, ledger.now) {
+                break;
+            }
+            let amount_move_to_available: f32 = authorization.amount.unwrap_or(0.0);
+            let currency: Option<String> = authorization.currency.clone();
+            let (available, held) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &currency);
+            *held -= amount_move_to_available;
+            *available += amount_move_to_available;
+            authorization.authorized = false;
+            let expiry: Transaction = Transaction {
+                tx_type: "expire".to_string(),
+                client_id,
+                tx,
+                amount: Some(amount_move_to_available),
+                row: ledger.row,
+                timestamp: ledger.now,
+                currency,
+                ..Default::default()
+            };
+            ledger.journal.post(&expiry, &expiry.currency, LedgerAccount::ClientHeld(client_id), LedgerAccount::ClientAvailable(client_id), amount_move_to_available);
+            add_transaction_to_account(account, &expiry);
+        }
+        ledger.pending_authorizations.pop_front();
+    }
+}
+
+fn is_authorization_expired(authorization: &Transaction, config: &Config, row: u64, now: Option<i64>) -> bool {
+    is_past_window(authorization, config.authorization_expiry_rows, config.authorization_expiry_secs, row, now)
 }
 
-fn apply_transaction_to_account(transaction: &mut Transaction, account: &mut Account) {
+fn is_dispute_window_expired(deposit: &Transaction, config: &Config, row: u64, now: Option<i64>) -> bool {
+    is_past_window(deposit, config.dispute_window_rows, config.dispute_window_secs, row, now)
+}
+
+// Whether more than `window_rows` rows or `window_secs` seconds have passed since the
+// entry. Entries without a timestamp only expire by rows.
+fn is_past_window(entry: &Transaction, window_rows: Option<u64>, window_secs: Option<i64>, row: u64, now: Option<i64>) -> bool {
+    let expired_by_rows: bool = window_rows
+        .is_some_and(|window_rows| entry.row.saturating_add(window_rows) < row);
+    let expired_by_time: bool = match (window_secs, entry.timestamp, now) {
+        (Some(window_secs), Some(entry_time), Some(now)) => entry_time.saturating_add(window_secs) < now,
+        _ => false,
+    };
+    expired_by_rows || expired_by_time
+}
+
+// Disputes are only accepted within the configured window after the deposit
+fn check_dispute_window(transaction: &Transaction, account: &Account, config: &Config, now: Option<i64>) -> Result<(), Rejection> {
+    if config.dispute_window_rows.is_none() && config.dispute_window_secs.is_none() {
+        return Ok(());
+    }
+    let deposit: Option<&Transaction> = account.transactions.iter()
+        .find(|deposit| deposit.tx == transaction.tx && deposit.tx_type == "deposit");
+    if deposit.is_some_and(|deposit| is_dispute_window_expired(deposit, config, transaction.row, now)) {
+        return Err(Rejection::DisputeWindowExpired);
+    }
+    Ok(())
+}
+
+fn apply_transaction_to_account(transaction: &mut Transaction, account: &mut Account, lock_policy: &LockPolicy, journal: &mut Journal) -> Result<(), Rejection> {
+    let client_id: u16 = transaction.client_id;
     match transaction.tx_type.as_str() {
         "deposit" => {
-            if let Some(deposit_amount) = transaction.amount {
-                account.available += deposit_amount;
-                add_transaction_to_account(account, transaction);
+            let Some(deposit_amount) = transaction.amount else {
+                return Err(Rejection::MissingAmount);
             };
+            let (available, _) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            *available += deposit_amount;
+            journal.post(transaction, &transaction.currency, LedgerAccount::Settlement, LedgerAccount::ClientAvailable(client_id), deposit_amount);
+            add_transaction_to_account(account, transaction);
         },
         "withdrawal" => {
-             if let Some(withdrawal_amount) = transaction.amount 
-                && account.available >= withdrawal_amount {
-                     account.available -= withdrawal_amount;
-                     add_transaction_to_account(account, transaction);
-                
+            let Some(withdrawal_amount) = transaction.amount else {
+                return Err(Rejection::MissingAmount);
             };
+            if let Some(timestamp) = transaction.timestamp
+                && account.withdrawal_day != Some(day_of(timestamp)) {
+                    account.withdrawal_day = Some(day_of(timestamp));
+                    account.daily_withdrawn = 0.0;
+                }
+            check_withdrawal_limits(account, withdrawal_amount, transaction.row)?;
+            if account.snapshot(&transaction.currency).available + account.limits.overdraft < withdrawal_amount {
+                return Err(Rejection::InsufficientFunds);
+            }
+            let (available, _) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            *available -= withdrawal_amount;
+            account.daily_withdrawn += withdrawal_amount;
+            // Rows are only kept for the count limit, which prunes them by its window
+            if account.limits.max_withdrawals.is_some() {
+                account.recent_withdrawals.push_back(transaction.row);
+            }
+            journal.post(transaction, &transaction.currency, LedgerAccount::ClientAvailable(client_id), LedgerAccount::Settlement, withdrawal_amount);
+            add_transaction_to_account(account, transaction);
         },
         "dispute" => {
             let search_transaction: Option<&mut Transaction> = search_matching_deposit_transaction(transaction.tx, &mut account.transactions, false);
-            if let Some(deposit_transaction) = search_transaction
-                && deposit_transaction.amount.unwrap() <= account.available {
-                    let amount_move_to_held: f32 = deposit_transaction.amount.unwrap();
-                    account.available -= amount_move_to_held;
-                    account.held += amount_move_to_held;
-                    deposit_transaction.disputed = true;
-                    add_transaction_to_account(account, transaction);
-                }
+            let Some(deposit_transaction) = search_transaction else {
+                return Err(Rejection::NoMatchingTransaction);
+            };
+            match_currency(transaction, deposit_transaction)?;
+            let amount_move_to_held: f32 = take_portion(deposit_transaction, undisputed_amount(deposit_transaction), transaction.amount)?;
+            let (available, held) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            if amount_move_to_held > *available {
+                return Err(Rejection::InsufficientFunds);
+            }
+            *available -= amount_move_to_held;
+            *held += amount_move_to_held;
+            deposit_transaction.disputed_amount += amount_move_to_held;
+            deposit_transaction.disputed = true;
+            transaction.amount = Some(amount_move_to_held);
+            journal.post(transaction, &transaction.currency, LedgerAccount::ClientAvailable(client_id), LedgerAccount::ClientHeld(client_id), amount_move_to_held);
+            add_transaction_to_account(account, transaction);
         },
         "resolve" => {
             let search_transaction: Option<&mut Transaction> = search_matching_disputed_transaction(transaction.tx, &mut account.transactions);
-            if let Some(deposit_transaction) = search_transaction {
-                let amount_move_to_available: f32 = deposit_transaction.amount.unwrap();
-                account.held -= amount_move_to_available;
-                account.available += amount_move_to_available;
-                deposit_transaction.disputed = false;
-                add_transaction_to_account(account, transaction);
-            }
+            let Some(deposit_transaction) = search_transaction else {
+                return Err(Rejection::NoMatchingTransaction);
+            };
+            match_currency(transaction, deposit_transaction)?;
+            let amount_move_to_available: f32 = take_portion(deposit_transaction, deposit_transaction.disputed_amount, transaction.amount)?;
+            let (available, held) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            *held -= amount_move_to_available;
+            *available += amount_move_to_available;
+            settle_disputed_amount(deposit_transaction, amount_move_to_available);
+            transaction.amount = Some(amount_move_to_available);
+            journal.post(transaction, &transaction.currency, LedgerAccount::ClientHeld(client_id), LedgerAccount::ClientAvailable(client_id), amount_move_to_available);
+            add_transaction_to_account(account, transaction);
         },
         "chargeback" => {
             let disputed_transaction: Option<&mut Transaction> = search_matching_disputed_transaction(transaction.tx, &mut account.transactions);
-            if let Some(deposit_transaction) = disputed_transaction {
-                account.held -= deposit_transaction.amount.unwrap();
+            let Some(deposit_transaction) = disputed_transaction else {
+                return Err(Rejection::NoMatchingTransaction);
+            };
+            match_currency(transaction, deposit_transaction)?;
+            let amount_charged_back: f32 = take_portion(deposit_transaction, deposit_transaction.disputed_amount, transaction.amount)?;
+            let (_, held) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            *held -= amount_charged_back;
+            if !deposit_transaction.charged_back {
+                account.chargebacks += 1;
+            }
+            if lock_policy.locks(account.chargebacks, amount_charged_back) {
                 account.locked = true;
-                add_transaction_to_account(account, transaction);
+            } else {
+                account.flagged = true;
             }
+            settle_disputed_amount(deposit_transaction, amount_charged_back);
+            deposit_transaction.charged_back_amount += amount_charged_back;
+            deposit_transaction.charged_back = true;
+            transaction.amount = Some(amount_charged_back);
+            // The chargeback loss account clears the refund to the card issuer against the
+            // client's held funds
+            journal.post(transaction, &transaction.currency, LedgerAccount::ChargebackLoss, LedgerAccount::Settlement, amount_charged_back);
+            journal.post(transaction, &transaction.currency, LedgerAccount::ClientHeld(client_id), LedgerAccount::ChargebackLoss, amount_charged_back);
+            add_transaction_to_account(account, transaction);
         },
-        _ => {},
+        "chargeback_reversal" => {
+            let search_transaction: Option<&mut Transaction> = search_charged_back_deposit(transaction.tx, &mut account.transactions);
+            let Some(deposit_transaction) = search_transaction else {
+                return Err(Rejection::NoMatchingTransaction);
+            };
+            match_currency(transaction, deposit_transaction)?;
+            let amount_reversed: f32 = take_portion(deposit_transaction, deposit_transaction.charged_back_amount, transaction.amount)?;
+            let (available, _) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            *available += amount_reversed;
+            deposit_transaction.charged_back_amount -= amount_reversed;
+            if deposit_transaction.charged_back_amount <= rounding_tolerance(deposit_transaction) {
+                deposit_transaction.charged_back_amount = 0.0;
+                deposit_transaction.charged_back = false;
+                account.chargebacks -= 1;
+            }
+            transaction.amount = Some(amount_reversed);
+            // The reverse of a chargeback: the card issuer returns the funds, which go
+            // back to the client's available funds
+            journal.post(transaction, &transaction.currency, LedgerAccount::Settlement, LedgerAccount::ChargebackLoss, amount_reversed);
+            journal.post(transaction, &transaction.currency, LedgerAccount::ChargebackLoss, LedgerAccount::ClientAvailable(client_id), amount_reversed);
+            add_transaction_to_account(account, transaction);
+        },
+        "authorize" => {
+            let Some(authorize_amount) = transaction.amount else {
+                return Err(Rejection::MissingAmount);
+            };
+            if account.snapshot(&transaction.currency).available + account.limits.overdraft < authorize_amount {
+                return Err(Rejection::InsufficientFunds);
+            }
+            let (available, held) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            *available -= authorize_amount;
+            *held += authorize_amount;
+            transaction.authorized = true;
+            journal.post(transaction, &transaction.currency, LedgerAccount::ClientAvailable(client_id), LedgerAccount::ClientHeld(client_id), authorize_amount);
+            add_transaction_to_account(account, transaction);
+        },
+        "capture" => {
+            let search_transaction: Option<&mut Transaction> = search_matching_authorization(transaction.tx, &mut account.transactions);
+            let Some(authorization) = search_transaction else {
+                return Err(Rejection::NoMatchingTransaction);
+            };
+            match_currency(transaction, authorization)?;
+            let authorized_amount: f32 = authorization.amount.unwrap_or(0.0);
+            let capture_amount: f32 = transaction.amount.unwrap_or(authorized_amount);
+            if capture_amount > authorized_amount {
+                return Err(Rejection::CaptureExceedsAuthorization);
+            }
+            let (available, held) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            *held -= authorized_amount;
+            *available += authorized_amount - capture_amount;
+            authorization.authorized = false;
+            transaction.amount = Some(capture_amount);
+            journal.post(transaction, &transaction.currency, LedgerAccount::ClientHeld(client_id), LedgerAccount::Settlement, capture_amount);
+            journal.post(transaction, &transaction.currency, LedgerAccount::ClientHeld(client_id), LedgerAccount::ClientAvailable(client_id), authorized_amount - capture_amount);
+            add_transaction_to_account(account, transaction);
+        },
+        "release" => {
+            let search_transaction: Option<&mut Transaction> = search_matching_authorization(transaction.tx, &mut account.transactions);
+            let Some(authorization) = search_transaction else {
+                return Err(Rejection::NoMatchingTransaction);
+            };
+            match_currency(transaction, authorization)?;
+            let authorized_amount: f32 = authorization.amount.unwrap_or(0.0);
+            let (available, held) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+            *held -= authorized_amount;
+            *available += authorized_amount;
+            authorization.authorized = false;
+            transaction.amount = Some(authorized_amount);
+            journal.post(transaction, &transaction.currency, LedgerAccount::ClientHeld(client_id), LedgerAccount::ClientAvailable(client_id), authorized_amount);
+            add_transaction_to_account(account, transaction);
+        },
+        _ => return Err(Rejection::UnknownType),
+    }
+    Ok(())
+}
+
+// Moves funds from the transaction's currency into its target currency at the rate
+// for the transaction's day. The move is recorded as a `convert_debit` entry in the
+// source currency followed by a linked `convert_credit` entry with the same tx id.
+fn apply_conversion(transaction: &mut Transaction, account: &mut Account, config: &Config, journal: &mut Journal) -> Result<(), Rejection> {
+    let Some(debit_amount) = transaction.amount else {
+        return Err(Rejection::MissingAmount);
+    };
+    let (Some(from), Some(to)) = (&transaction.currency, &transaction.target_currency) else {
+        return Err(Rejection::MissingCurrency);
+    };
+    if from == to {
+        return Err(Rejection::SameCurrencyConversion);
+    }
+    let Some(rate) = config.rates.rate(from, to, transaction.timestamp.map(day_of)) else {
+        return Err(Rejection::RateUnavailable);
+    };
+    let decimals: u32 = config.fx_decimals.unwrap_or(4);
+    let converted_amount: f32 = config.fx_rounding.apply(debit_amount * rate, decimals);
+    let spread_amount: f32 = config.fx_rounding.apply(debit_amount * rate * (1.0 - config.fx_spread), decimals);
+    let credit_amount: f32 = config.fx_rounding.apply(spread_amount - config.fx_fee, decimals);
+    if credit_amount < 0.0 {
+        return Err(Rejection::FeeExceedsConversion);
+    }
+
+    if account.snapshot(&transaction.currency).available + account.limits.overdraft < debit_amount {
+        return Err(Rejection::InsufficientFunds);
     }
+    let (available, _) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.currency);
+    *available -= debit_amount;
+    let debit: Transaction = Transaction {
+        tx_type: "convert_debit".to_string(),
+        ..transaction.clone()
+    };
+    journal.post(transaction, &transaction.currency, LedgerAccount::ClientAvailable(transaction.client_id), LedgerAccount::Settlement, debit_amount);
+    add_transaction_to_account(account, &debit);
+
+    let (available, _) = currency_funds(&mut account.available, &mut account.held, &mut account.currencies, &transaction.target_currency);
+    *available += credit_amount;
+    let credit: Transaction = Transaction {
+        tx_type: "convert_credit".to_string(),
+        amount: Some(credit_amount),
+        currency: transaction.target_currency.clone(),
+        ..transaction.clone()
+    };
+    // The spread and the flat fee are booked to fees
+    journal.post(transaction, &credit.currency, LedgerAccount::Settlement, LedgerAccount::ClientAvailable(transaction.client_id), credit_amount);
+    journal.post(transaction, &credit.currency, LedgerAccount::Settlement, LedgerAccount::Fees, converted_amount - credit_amount);
+    add_transaction_to_account(account, &credit);
+    Ok(())
+}
+
+// Returns the available and held funds for a currency, creating the balance if it
+// is new. Transactions without a currency use the account's own `available` and
+// `held` fields. Checks that may reject the transaction should read the balance
+// through `Account::snapshot` first, so that a rejection leaves no empty balance.
+fn currency_funds<'a>(
+    available: &'a mut f32,
+    held: &'a mut f32,
+    currencies: &'a mut BTreeMap<String, Balance>,
+    currency: &Option<String>,
+) -> (&'a mut f32, &'a mut f32) {
+    match currency {
+        Some(currency) => {
+            let balance: &mut Balance = currencies.entry(currency.clone()).or_default();
+            (&mut balance.available, &mut balance.held)
+        },
+        None => (available, held),
+    }
+}
+
+// A row referring to an earlier transaction acts in that transaction's currency.
+// It may leave its own currency empty, but may not name a different one.
+fn match_currency(transaction: &mut Transaction, referenced_transaction: &Transaction) -> Result<(), Rejection> {
+    if transaction.currency.is_some() && transaction.currency != referenced_transaction.currency {
+        return Err(Rejection::CurrencyMismatch);
+    }
+    transaction.currency = referenced_transaction.currency.clone();
+    Ok(())
 }
 
+// The daily total is reset on the first withdrawal of each new calendar day. Rows
+// without a timestamp count towards the current day, so without timestamps the
+// daily limit applies to the whole file
+fn check_withdrawal_limits(account: &mut Account, withdrawal_amount: f32, row: u64) -> Result<(), Rejection> {
+    if account.limits.max_withdrawal.is_some_and(|max_withdrawal| withdrawal_amount > max_withdrawal) {
+        return Err(Rejection::MaxWithdrawalExceeded);
+    }
+    if account.limits.max_daily_withdrawal.is_some_and(|max_daily_withdrawal| account.daily_withdrawn + withdrawal_amount > max_daily_withdrawal) {
+        return Err(Rejection::DailyWithdrawalLimitExceeded);
+    }
+    if let Some(max_withdrawals) = account.limits.max_withdrawals {
+        if let Some(window) = account.limits.withdrawal_window {
+            while account.recent_withdrawals.front().is_some_and(|&withdrawal_row| withdrawal_row.saturating_add(window) <= row) {
+                account.recent_withdrawals.pop_front();
+            }
+        }
+        if account.recent_withdrawals.len() >= max_withdrawals as usize {
+            return Err(Rejection::WithdrawalCountLimitExceeded);
+        }
+    }
+    Ok(())
+}
+
+// With `is_disputed`, finds a deposit with a part under dispute, otherwise one with a
+// part that can still be disputed
 fn search_matching_deposit_transaction(tx: u32, account_transactions: &mut Vec<Transaction>, is_disputed: bool) -> Option<&mut Transaction> {
     for transaction in account_transactions.as_mut_slice() {
         if transaction.tx == tx && transaction.tx_type == "deposit" && transaction.amount.is_some() {
-            if transaction.disputed == is_disputed {
+            let has_portion: bool = match is_disputed {
+                true => transaction.disputed,
+                false => undisputed_amount(transaction) > rounding_tolerance(transaction),
+            };
+            if has_portion {
                 return Some(transaction);
             }
             break
@@ -104,6 +684,38 @@ fn search_matching_deposit_transaction(tx: u32, account_transactions: &mut Vec<T
     None
 }
 
+// The part of a deposit that is neither under dispute nor charged back
+fn undisputed_amount(deposit: &Transaction) -> f32 {
+    deposit.amount.unwrap_or(0.0) - deposit.disputed_amount - deposit.charged_back_amount
+}
+
+// The part of a deposit's `remainder` a dispute, resolve or chargeback acts on: all of
+// it when the row carries no amount, otherwise the row's amount. An amount within
+// rounding of the remainder takes all of it, so that partial rows leave no residue.
+// A zero amount would open or settle a dispute that moves nothing, so it is rejected.
+fn take_portion(deposit: &Transaction, remainder: f32, amount: Option<f32>) -> Result<f32, Rejection> {
+    match amount {
+        None => Ok(remainder),
+        Some(amount) if amount <= rounding_tolerance(deposit) => Err(Rejection::ZeroDisputeAmount),
+        Some(amount) if (amount - remainder).abs() <= rounding_tolerance(deposit) => Ok(remainder),
+        Some(amount) if amount > remainder => Err(Rejection::DisputeAmountExceeded),
+        Some(amount) => Ok(amount),
+    }
+}
+
+fn settle_disputed_amount(deposit: &mut Transaction, amount: f32) {
+    deposit.disputed_amount -= amount;
+    if deposit.disputed_amount <= rounding_tolerance(deposit) {
+        deposit.disputed_amount = 0.0;
+        deposit.disputed = false;
+    }
+}
+
+// A few units in the last place of f32 at the size of the deposit
+fn rounding_tolerance(deposit: &Transaction) -> f32 {
+    8.0 * f32::EPSILON * deposit.amount.unwrap_or(0.0).abs().max(1.0)
+}
+
 fn search_matching_disputed_transaction(tx: u32, account_transactions: &mut Vec<Transaction>) -> Option<&mut Transaction> {
     for transaction in account_transactions.iter() {
         if transaction.tx == tx && transaction.tx_type == "dispute" {
@@ -113,15 +725,45 @@ fn search_matching_disputed_transaction(tx: u32, account_transactions: &mut Vec<
     None
 }
 
+fn search_charged_back_deposit(tx: u32, account_transactions: &mut [Transaction]) -> Option<&mut Transaction> {
+    account_transactions.iter_mut().find(|transaction| {
+        transaction.tx == tx && transaction.tx_type == "deposit" && transaction.charged_back
+    })
+}
+
+// The account is unlocked once the chargebacks still standing would not have locked
+// it under the lock policy, judging the amount by the largest of them
+fn unlock_after_reversal(account: &mut Account, lock_policy: &LockPolicy) {
+    let largest_charged_back: f32 = account.transactions.iter()
+        .filter(|deposit| deposit.tx_type == "deposit" && deposit.charged_back)
+        .map(|deposit| deposit.charged_back_amount)
+        .fold(0.0, f32::max);
+    if account.chargebacks == 0 || !lock_policy.locks(account.chargebacks, largest_charged_back) {
+        account.locked = false;
+    }
+}
+
+fn search_matching_authorization(tx: u32, account_transactions: &mut [Transaction]) -> Option<&mut Transaction> {
+    account_transactions.iter_mut().find(|transaction| {
+        transaction.tx == tx && transaction.tx_type == "authorize" && transaction.authorized
+    })
+}
+
 fn add_transaction_to_account(account: &mut Account, transaction: &Transaction) {
+    let mut recorded_transaction: Transaction = transaction.clone();
+    recorded_transaction.snapshot = account.snapshot(&transaction.currency);
+    *account.history_totals.entry(transaction.currency.clone()).or_default() += funds_movement(transaction);
     account.transactions.push(
-        transaction.clone()
+        recorded_transaction
     );
 }
 
 #[cfg(test)]
+// The original tests compare booleans with `assert_eq!`
+#[allow(clippy::bool_assert_comparison)]
 mod unittests {
     use super::*;
+    use crate::config::{AccountLimits, LockPolicy};
 
     #[test]
     fn test_process_transaction() {
@@ -131,11 +773,12 @@ mod unittests {
             tx: 1,
             amount: Some(100.0),
             disputed: false,
+            ..Default::default()
         };
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_transaction(transaction, accounts);
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_transaction(transaction, ledger).unwrap();
 
-        let account: &Account = accounts.get(&1).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
         assert_eq!(account.available, 100.0);
         assert_eq!(account.held, 0.0);
         assert_eq!(account.locked, false);
@@ -151,6 +794,7 @@ mod unittests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -158,6 +802,7 @@ mod unittests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "chargeback".to_string(),
@@ -165,15 +810,16 @@ mod unittests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
+        let ledger: &mut Ledger = &mut Ledger::default();
         
         for transaction in transactions.iter_mut() {
-            process_transaction(transaction, accounts);
+            process_transaction(transaction, ledger).unwrap();
         }
 
-        let account: &Account = accounts.get(&1).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
         assert_eq!(account.available, 0.0);
         assert_eq!(account.held, 0.0);
         assert!(account.locked);
@@ -188,15 +834,17 @@ mod unittests {
             tx: 1,
             amount: Some(100.0),
             disputed: false,
+            ..Default::default()
         };
         let mut account: Account = Account {
             available: 0.0,
             held: 0.0,
             locked: false,
             transactions: vec![],
+            ..Default::default()
         };
 
-        apply_transaction_to_account(&mut transaction, &mut account);
+        apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
 
         assert_eq!(account.available, 100.0);
         assert_eq!(account.held, 0.0);
@@ -211,19 +859,22 @@ mod unittests {
             tx: 1,
             amount: Some(50.0),
             disputed: false,
+            ..Default::default()
         };
         let mut account: Account = Account {
             available: 100.0,
             held: 0.0,
             locked: false,
             transactions: vec![],
+            ..Default::default()
         };
 
-        apply_transaction_to_account(&mut transaction, &mut account);
+        apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
 
         assert_eq!(account.available, 50.0);
         assert_eq!(account.held, 0.0);
         assert_eq!(account.locked, false);
+        assert!(account.recent_withdrawals.is_empty());
     }
 
     #[test]
@@ -234,6 +885,7 @@ mod unittests {
             tx: 2,
             amount: None,
             disputed: false,
+            ..Default::default()
         };
         let mut account: Account = Account {
             available: 150.0,
@@ -246,6 +898,7 @@ mod unittests {
                     tx: 1,
                     amount: Some(100.0),
                     disputed: false,
+                    ..Default::default()
                 },
                 Transaction {
                     tx_type: "deposit".to_string(),
@@ -253,11 +906,13 @@ mod unittests {
                     tx: 2,
                     amount: Some(50.0),
                     disputed: false,
+                    ..Default::default()
                 }
             ],
+            ..Default::default()
         };
 
-        apply_transaction_to_account(&mut transaction, &mut account);
+        apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
 
         assert_eq!(account.available, 100.0);
         assert_eq!(account.held, 50.0);
@@ -273,6 +928,7 @@ mod unittests {
             tx: 2,
             amount: None,
             disputed: false,
+            ..Default::default()
         };
         let mut account: Account = Account {
             available: 100.0,
@@ -285,6 +941,7 @@ mod unittests {
                     tx: 1,
                     amount: Some(100.0),
                     disputed: false,
+                    ..Default::default()
                 },
                 Transaction {
                     tx_type: "deposit".to_string(),
@@ -292,6 +949,8 @@ mod unittests {
                     tx: 2,
                     amount: Some(50.0),
                     disputed: true,
+                    disputed_amount: 50.0,
+                    ..Default::default()
                 },
                 Transaction {
                     tx_type: "dispute".to_string(),
@@ -299,11 +958,13 @@ mod unittests {
                     tx: 2,
                     amount: None,
                     disputed: false,
+                    ..Default::default()
                 }
             ],
+            ..Default::default()
         };
 
-        apply_transaction_to_account(&mut transaction, &mut account);
+        apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
 
         assert_eq!(account.available, 150.0);
         assert_eq!(account.held, 0.0);
@@ -319,6 +980,7 @@ mod unittests {
             tx: 2,
             amount: None,
             disputed: false,
+            ..Default::default()
         };
         let mut account: Account = Account {
             available: 100.0,
@@ -331,6 +993,7 @@ mod unittests {
                     tx: 1,
                     amount: Some(100.0),
                     disputed: false,
+                    ..Default::default()
                 },
                 Transaction {
                     tx_type: "deposit".to_string(),
@@ -338,6 +1001,8 @@ mod unittests {
                     tx: 2,
                     amount: Some(50.0),
                     disputed: true,
+                    disputed_amount: 50.0,
+                    ..Default::default()
                 },
                 Transaction {
                     tx_type: "dispute".to_string(),
@@ -345,15 +1010,261 @@ mod unittests {
                     tx: 2,
                     amount: None,
                     disputed: false,
+                    ..Default::default()
                 }
             ],
+            ..Default::default()
         };
 
-        apply_transaction_to_account(&mut transaction, &mut account);
+        apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
 
         assert_eq!(account.available, 100.0);
         assert_eq!(account.held, 0.0);
         assert_eq!(account.locked, true);
+        assert!(!account.transactions[1].disputed);
+        assert!(account.transactions[1].charged_back);
+    }
+
+    #[test]
+    fn test_apply_transaction_to_account_authorize() {
+        let mut transaction: Transaction = Transaction {
+            tx_type: "authorize".to_string(),
+            client_id: 1,
+            tx: 2,
+            amount: Some(40.0),
+            ..Default::default()
+        };
+        let mut account: Account = Account {
+            available: 100.0,
+            ..Default::default()
+        };
+
+        apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
+
+        assert_eq!(account.available, 60.0);
+        assert_eq!(account.held, 40.0);
+        assert!(account.transactions[0].authorized);
+    }
+
+    #[test]
+    fn test_apply_transaction_to_account_authorize_insufficient_funds() {
+        let mut transaction: Transaction = Transaction {
+            tx_type: "authorize".to_string(),
+            client_id: 1,
+            tx: 2,
+            amount: Some(140.0),
+            ..Default::default()
+        };
+        let mut account: Account = Account {
+            available: 100.0,
+            ..Default::default()
+        };
+
+        let result: Result<(), Rejection> = apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default());
+
+        assert_eq!(result, Err(Rejection::InsufficientFunds));
+
+        assert_eq!(account.available, 100.0);
+        assert_eq!(account.held, 0.0);
+        assert!(account.transactions.is_empty());
+    }
+
+    #[test]
+    fn test_apply_transaction_to_account_capture() {
+        let mut transaction: Transaction = Transaction {
+            tx_type: "capture".to_string(),
+            client_id: 1,
+            tx: 2,
+            amount: Some(30.0),
+            ..Default::default()
+        };
+        let mut account: Account = Account {
+            available: 60.0,
+            held: 40.0,
+            transactions: vec![
+                Transaction {
+                    tx_type: "authorize".to_string(),
+                    client_id: 1,
+                    tx: 2,
+                    amount: Some(40.0),
+                    authorized: true,
+                    ..Default::default()
+                }
+            ],
+            ..Default::default()
+        };
+
+        apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
+
+        assert_eq!(account.available, 70.0);
+        assert_eq!(account.held, 0.0);
+        assert!(!account.transactions[0].authorized);
+        assert_eq!(account.transactions[1].amount, Some(30.0));
+    }
+
+    #[test]
+    fn test_apply_transaction_to_account_capture_exceeds_authorization() {
+        let mut transaction: Transaction = Transaction {
+            tx_type: "capture".to_string(),
+            client_id: 1,
+            tx: 2,
+            amount: Some(50.0),
+            ..Default::default()
+        };
+        let mut account: Account = Account {
+            available: 60.0,
+            held: 40.0,
+            transactions: vec![
+                Transaction {
+                    tx_type: "authorize".to_string(),
+                    client_id: 1,
+                    tx: 2,
+                    amount: Some(40.0),
+                    authorized: true,
+                    ..Default::default()
+                }
+            ],
+            ..Default::default()
+        };
+
+        let result: Result<(), Rejection> = apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default());
+
+        assert_eq!(result, Err(Rejection::CaptureExceedsAuthorization));
+        assert_eq!(account.held, 40.0);
+        assert!(account.transactions[0].authorized);
+    }
+
+    #[test]
+    fn test_apply_transaction_to_account_release() {
+        let mut transaction: Transaction = Transaction {
+            tx_type: "release".to_string(),
+            client_id: 1,
+            tx: 2,
+            amount: None,
+            ..Default::default()
+        };
+        let mut account: Account = Account {
+            available: 60.0,
+            held: 40.0,
+            transactions: vec![
+                Transaction {
+                    tx_type: "authorize".to_string(),
+                    client_id: 1,
+                    tx: 2,
+                    amount: Some(40.0),
+                    authorized: true,
+                    ..Default::default()
+                }
+            ],
+            ..Default::default()
+        };
+
+        apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
+
+        assert_eq!(account.available, 100.0);
+        assert_eq!(account.held, 0.0);
+        assert!(!account.transactions[0].authorized);
+    }
+
+    #[test]
+    fn test_search_matching_authorization() {
+        let mut account_transactions: Vec<Transaction> = vec![
+            Transaction {
+                tx_type: "authorize".to_string(),
+                client_id: 1,
+                tx: 1,
+                amount: Some(100.0),
+                authorized: false,
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(50.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "authorize".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(20.0),
+                authorized: true,
+                ..Default::default()
+            },
+        ];
+
+        assert!(search_matching_authorization(1, &mut account_transactions).is_none());
+        match search_matching_authorization(2, &mut account_transactions) {
+            Some(transaction) => {
+                assert_eq!(transaction.tx_type, "authorize".to_string());
+                assert_eq!(transaction.amount, Some(20.0));
+            },
+            None => panic!("Matching authorization not found"),
+        }
+    }
+
+    #[test]
+    fn test_check_withdrawal_limits_max_withdrawal() {
+        let mut account: Account = Account {
+            limits: AccountLimits {
+                max_withdrawal: Some(50.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(check_withdrawal_limits(&mut account, 50.0, 1), Ok(()));
+        assert_eq!(check_withdrawal_limits(&mut account, 50.5, 1), Err(Rejection::MaxWithdrawalExceeded));
+    }
+
+    #[test]
+    fn test_check_withdrawal_limits_daily_withdrawal() {
+        let mut account: Account = Account {
+            daily_withdrawn: 80.0,
+            limits: AccountLimits {
+                max_daily_withdrawal: Some(100.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(check_withdrawal_limits(&mut account, 20.0, 1), Ok(()));
+        assert_eq!(check_withdrawal_limits(&mut account, 21.0, 1), Err(Rejection::DailyWithdrawalLimitExceeded));
+    }
+
+    #[test]
+    fn test_check_withdrawal_limits_window() {
+        let mut account: Account = Account {
+            recent_withdrawals: VecDeque::from(vec![1, 4]),
+            limits: AccountLimits {
+                max_withdrawals: Some(2),
+                withdrawal_window: Some(5),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(check_withdrawal_limits(&mut account, 1.0, 5), Err(Rejection::WithdrawalCountLimitExceeded));
+        assert_eq!(check_withdrawal_limits(&mut account, 1.0, 6), Ok(()));
+        assert_eq!(account.recent_withdrawals.len(), 1);
+    }
+
+    #[test]
+    fn test_apply_transaction_to_account_unknown_type() {
+        let mut transaction: Transaction = Transaction {
+            tx_type: "refund".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: Some(10.0),
+            ..Default::default()
+        };
+        let mut account: Account = Account::default();
+
+        let result: Result<(), Rejection> = apply_transaction_to_account(&mut transaction, &mut account, &LockPolicy::default(), &mut Journal::default());
+
+        assert_eq!(result, Err(Rejection::UnknownType));
+        assert!(account.transactions.is_empty());
     }
 
     #[test]
@@ -365,6 +1276,7 @@ mod unittests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -372,6 +1284,7 @@ mod unittests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
         ];
         let result: Option<&mut Transaction> = search_matching_deposit_transaction(2, &mut account_transactions, false);
@@ -396,6 +1309,7 @@ mod unittests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -403,6 +1317,7 @@ mod unittests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: true,
+                ..Default::default()
             },
         ];
         let result: Option<&mut Transaction> = search_matching_deposit_transaction(2, &mut account_transactions, true);
@@ -427,6 +1342,7 @@ mod unittests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -434,6 +1350,7 @@ mod unittests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: true,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -441,6 +1358,7 @@ mod unittests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             }
         ];
         let result: Option<&mut Transaction> = search_matching_disputed_transaction(2, &mut account_transactions);
@@ -463,6 +1381,7 @@ mod unittests {
             held: 0.0,
             locked: false,
             transactions: vec![],
+            ..Default::default()
         };
         let transaction: Transaction = Transaction {
             tx_type: "deposit".to_string(),
@@ -470,6 +1389,7 @@ mod unittests {
             tx: 1,
             amount: Some(100.0),
             disputed: false,
+            ..Default::default()
         };
 
         add_transaction_to_account(&mut account, &transaction);
@@ -482,4 +1402,235 @@ mod unittests {
         assert_eq!(added_transaction.amount, Some(100.0));
         assert_eq!(added_transaction.disputed, false);
     }
+
+    #[test]
+    fn test_add_transaction_to_account_records_snapshot() {
+        let mut account: Account = Account {
+            available: 60.0,
+            held: 40.0,
+            locked: true,
+            ..Default::default()
+        };
+        let transaction: Transaction = Transaction {
+            tx_type: "chargeback".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: None,
+            ..Default::default()
+        };
+
+        add_transaction_to_account(&mut account, &transaction);
+
+        assert_eq!(account.transactions[0].snapshot, Snapshot { available: 60.0, held: 40.0, locked: true, ..Default::default() });
+    }
+
+    #[test]
+    fn test_apply_transaction_to_account_currency_balances() {
+        let mut account: Account = Account::default();
+        let mut deposit: Transaction = Transaction {
+            tx_type: "deposit".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: Some(100.0),
+            currency: Some("EUR".to_string()),
+            ..Default::default()
+        };
+        let mut withdrawal: Transaction = Transaction {
+            tx_type: "withdrawal".to_string(),
+            client_id: 1,
+            tx: 2,
+            amount: Some(30.0),
+            currency: Some("GBP".to_string()),
+            ..Default::default()
+        };
+
+        apply_transaction_to_account(&mut deposit, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
+        let result: Result<(), Rejection> = apply_transaction_to_account(&mut withdrawal, &mut account, &LockPolicy::default(), &mut Journal::default());
+
+        assert_eq!(result, Err(Rejection::InsufficientFunds));
+        assert_eq!(account.available, 0.0);
+        assert_eq!(account.currencies.get("EUR"), Some(&Balance { available: 100.0, held: 0.0 }));
+        assert!(!account.currencies.contains_key("GBP"));
+        assert_eq!(account.transactions[0].snapshot, Snapshot { available: 100.0, held: 0.0, locked: false, ..Default::default() });
+    }
+
+    #[test]
+    fn test_apply_transaction_to_account_dispute_currency() {
+        let mut account: Account = Account::default();
+        let mut deposit: Transaction = Transaction {
+            tx_type: "deposit".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: Some(100.0),
+            currency: Some("EUR".to_string()),
+            ..Default::default()
+        };
+        let mut mismatched_dispute: Transaction = Transaction {
+            tx_type: "dispute".to_string(),
+            client_id: 1,
+            tx: 1,
+            currency: Some("GBP".to_string()),
+            ..Default::default()
+        };
+        let mut dispute: Transaction = Transaction {
+            tx_type: "dispute".to_string(),
+            client_id: 1,
+            tx: 1,
+            ..Default::default()
+        };
+
+        apply_transaction_to_account(&mut deposit, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
+        let result: Result<(), Rejection> = apply_transaction_to_account(&mut mismatched_dispute, &mut account, &LockPolicy::default(), &mut Journal::default());
+        assert_eq!(result, Err(Rejection::CurrencyMismatch));
+
+        apply_transaction_to_account(&mut dispute, &mut account, &LockPolicy::default(), &mut Journal::default()).unwrap();
+        assert_eq!(account.currencies.get("EUR"), Some(&Balance { available: 0.0, held: 100.0 }));
+        assert_eq!(account.transactions[1].currency, Some("EUR".to_string()));
+        assert_eq!(account.held, 0.0);
+    }
+
+    #[test]
+    fn test_apply_conversion() {
+        let mut config: Config = Config {
+            fx_spread: 0.01,
+            fx_decimals: Some(2),
+            ..Default::default()
+        };
+        config.rates.insert("EUR", "GBP", 0, 0.8567);
+        let mut account: Account = Account::default();
+        account.currencies.insert("EUR".to_string(), Balance { available: 200.0, held: 0.0 });
+        let mut transaction: Transaction = Transaction {
+            tx_type: "convert".to_string(),
+            client_id: 1,
+            tx: 5,
+            amount: Some(100.0),
+            currency: Some("EUR".to_string()),
+            target_currency: Some("GBP".to_string()),
+            ..Default::default()
+        };
+
+        apply_conversion(&mut transaction, &mut account, &config, &mut Journal::default()).unwrap();
+
+        assert_eq!(account.currencies.get("EUR"), Some(&Balance { available: 100.0, held: 0.0 }));
+        assert_eq!(account.currencies.get("GBP"), Some(&Balance { available: 84.81, held: 0.0 }));
+        assert_eq!(account.transactions.len(), 2);
+        assert_eq!(account.transactions[0].tx_type, "convert_debit");
+        assert_eq!(account.transactions[0].snapshot.available, 100.0);
+        assert_eq!(account.transactions[1].tx_type, "convert_credit");
+        assert_eq!(account.transactions[1].tx, 5);
+        assert_eq!(account.transactions[1].amount, Some(84.81));
+        assert_eq!(account.transactions[1].currency, Some("GBP".to_string()));
+    }
+
+    #[test]
+    fn test_apply_conversion_rejections() {
+        let config: Config = Config::default();
+        let mut account: Account = Account::default();
+        let mut no_target: Transaction = Transaction {
+            tx_type: "convert".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: Some(10.0),
+            currency: Some("EUR".to_string()),
+            ..Default::default()
+        };
+        let mut no_rate: Transaction = Transaction {
+            target_currency: Some("GBP".to_string()),
+            ..no_target.clone()
+        };
+
+        assert_eq!(apply_conversion(&mut no_target, &mut account, &config, &mut Journal::default()), Err(Rejection::MissingCurrency));
+        assert_eq!(apply_conversion(&mut no_rate, &mut account, &config, &mut Journal::default()), Err(Rejection::RateUnavailable));
+        assert!(account.transactions.is_empty());
+    }
+
+    #[test]
+    fn test_apply_conversion_fee() {
+        let mut config: Config = Config {
+            fx_decimals: Some(2),
+            fx_fee: 2.5,
+            ..Default::default()
+        };
+        config.rates.insert("EUR", "GBP", 0, 0.5);
+        let mut account: Account = Account::default();
+        account.currencies.insert("EUR".to_string(), Balance { available: 200.0, held: 0.0 });
+        let mut transaction: Transaction = Transaction {
+            tx_type: "convert".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: Some(100.0),
+            currency: Some("EUR".to_string()),
+            target_currency: Some("GBP".to_string()),
+            ..Default::default()
+        };
+        let mut below_fee: Transaction = Transaction {
+            tx: 2,
+            amount: Some(4.0),
+            ..transaction.clone()
+        };
+        let mut same_currency: Transaction = Transaction {
+            tx: 3,
+            target_currency: Some("EUR".to_string()),
+            ..transaction.clone()
+        };
+        let mut journal: Journal = Journal::default();
+
+        apply_conversion(&mut transaction, &mut account, &config, &mut journal).unwrap();
+        assert_eq!(account.currencies.get("GBP"), Some(&Balance { available: 47.5, held: 0.0 }));
+        assert_eq!(apply_conversion(&mut below_fee, &mut account, &config, &mut journal), Err(Rejection::FeeExceedsConversion));
+        assert_eq!(apply_conversion(&mut same_currency, &mut account, &config, &mut journal), Err(Rejection::SameCurrencyConversion));
+        assert_eq!(account.currencies.get("EUR"), Some(&Balance { available: 100.0, held: 0.0 }));
+        assert_eq!(account.transactions.len(), 2);
+    }
+
+    #[test]
+    fn test_prune_history() {
+        let entry = |tx_type: &str, tx: u32, amount: f32, row: u64| Transaction {
+            tx_type: tx_type.to_string(),
+            client_id: 1,
+            tx,
+            amount: Some(amount),
+            row,
+            ..Default::default()
+        };
+        let mut account: Account = Account {
+            transactions: vec![
+                entry("deposit", 1, 10.0, 1),
+                Transaction { disputed: true, ..entry("deposit", 2, 20.0, 2) },
+                entry("dispute", 2, 20.0, 3),
+                entry("withdrawal", 3, 5.0, 4),
+                Transaction { authorized: true, ..entry("authorize", 4, 1.0, 5) },
+                entry("authorize", 5, 2.0, 6),
+                entry("dispute", 1, 10.0, 7),
+                entry("resolve", 1, 10.0, 8),
+                entry("deposit", 6, 30.0, 18),
+            ],
+            ..Default::default()
+        };
+
+        prune_history(&mut account, |deposit| deposit.row + 5 <= 20, &mut None, &mut None);
+        let retained: Vec<(&str, u32)> = account.transactions.iter()
+            .map(|transaction| (transaction.tx_type.as_str(), transaction.tx))
+            .collect();
+
+        assert_eq!(retained, vec![("deposit", 2), ("dispute", 2), ("authorize", 4), ("deposit", 6)]);
+        assert_eq!(account.retained_history, 4);
+        assert_eq!(account.pruned_funds.get(&None), Some(&5.0));
+    }
+
+    #[test]
+    fn test_take_portion() {
+        let deposit: Transaction = Transaction {
+            tx_type: "deposit".to_string(),
+            amount: Some(100.0),
+            ..Default::default()
+        };
+
+        assert_eq!(take_portion(&deposit, 70.0, None), Ok(70.0));
+        assert_eq!(take_portion(&deposit, 70.0, Some(25.5)), Ok(25.5));
+        assert_eq!(take_portion(&deposit, 0.1 + 0.2, Some(0.3)), Ok(0.1 + 0.2));
+        assert_eq!(take_portion(&deposit, 70.0, Some(70.5)), Err(Rejection::DisputeAmountExceeded));
+        assert_eq!(take_portion(&deposit, 70.0, Some(0.0)), Err(Rejection::ZeroDisputeAmount));
+        assert_eq!(take_portion(&deposit, 0.0, Some(0.0)), Err(Rejection::ZeroDisputeAmount));
+    }
 }
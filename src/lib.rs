@@ -0,0 +1,19 @@
+// Parsing helpers report failure as `Err(())` and the caller skips the row
+#![allow(clippy::result_unit_err)]
+
+pub mod utils;
+pub mod ledger;
+pub mod config;
+pub mod dates;
+pub mod cli;
+pub mod statement;
+pub mod rates;
+pub mod journal;
+pub mod reconcile;
+mod tests;
+mod property_tests;
+pub mod generator;
+pub mod disputes;
+pub mod columns;
+pub mod dialects;
+pub mod json_lines;
@@ -1,17 +1,123 @@
-mod utils;
-mod ledger;
-mod tests;
-
-use std::{collections::HashMap, env};
-use crate::utils::{
+use std::{collections::{BTreeMap, HashMap}, env, fs::File, io::{BufWriter, Write}, process};
+use rust_ledger::utils::{
     output_accounts,
+    output_balances,
+    output_rejections,
     Account,
+    Snapshot,
 };
-use crate::ledger::summarize_accounts;
+use rust_ledger::ledger::{summarize_accounts, Ledger};
+use rust_ledger::config::{load_limits, Config};
+use rust_ledger::rates::load_rates;
+use rust_ledger::dialects::{load_profile, Profile};
+use rust_ledger::cli::{parse_args, Command, Options, USAGE};
+use rust_ledger::journal::{output_journal, output_trial_balance};
+use rust_ledger::reconcile::{output_reconciliation, reconcile, Discrepancy};
+use rust_ledger::generator::{generate, output_expected_balances};
+use rust_ledger::disputes::output_disputes;
+use rust_ledger::statement::{account_statement, output_statement};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let file_path: &String = &args[1];
-    let accounts: HashMap<u16, Account> = summarize_accounts(file_path);
-    output_accounts(accounts);
+    let mut options: Options = match parse_args(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => exit_with_error(&format!("{}\n{}", message, USAGE)),
+    };
+
+    if options.command == Command::Generate {
+        if let Err(err) = generate_files(&options) {
+            exit_with_error(&format!("Unable to write generated files: {}", err));
+        }
+        return;
+    }
+
+    let mut config: Config = std::mem::take(&mut options.config);
+    if let Some(limits_path) = &options.limits_path {
+        config.limits = match load_limits(limits_path) {
+            Ok(limits) => limits,
+            Err(message) => exit_with_error(&message),
+        };
+    }
+    if let (Some(profiles_path), Some(profile)) = (&options.profiles_path, &options.profile) {
+        let profile: Profile = match load_profile(profiles_path, profile) {
+            Ok(profile) => profile,
+            Err(message) => exit_with_error(&message),
+        };
+        config.dialect = profile.dialect;
+        // Aliases given on the command line take precedence over the profile's
+        for (header, column) in profile.column_aliases {
+            config.column_aliases.entry(header).or_insert(column);
+        }
+    }
+    if let Some(rates_path) = &options.rates_path {
+        config.rates = match load_rates(rates_path) {
+            Ok(rates) => rates,
+            Err(message) => exit_with_error(&message),
+        };
+    }
+
+    let ledger: Ledger = match summarize_accounts(&options.file_path, config) {
+        Ok(ledger) => ledger,
+        Err(message) => exit_with_error(&message),
+    };
+    if let Some(violation) = &ledger.violation {
+        exit_with_error(violation);
+    }
+    if let Some(rejections_path) = &options.rejections_path
+        && let Err(err) = output_rejections(&ledger.rejections, rejections_path) {
+            exit_with_error(&format!("Unable to write rejections file {}: {}", rejections_path, err));
+        }
+
+    match options.command {
+        Command::Summarize => summarize(ledger, &options),
+        Command::Statement => statement(ledger, &options),
+        Command::Journal => output_journal(&ledger.journal),
+        Command::TrialBalance => output_trial_balance(&ledger.journal.trial_balance()),
+        Command::Reconcile => reconciliation(ledger),
+        Command::Disputes => output_disputes(&ledger.dispute_records(options.client)),
+        Command::Generate => {},
+    }
+}
+
+fn summarize(ledger: Ledger, options: &Options) {
+    match options.as_of {
+        Some(date) => output_balances(ledger.balances_as_of(date)),
+        None => {
+            let accounts: HashMap<u16, Account> = ledger.accounts;
+            output_accounts(accounts);
+        },
+    }
+}
+
+fn statement(ledger: Ledger, options: &Options) {
+    let client_id: u16 = options.client.unwrap_or_default();
+    match ledger.accounts.get(&client_id) {
+        Some(account) => output_statement(&account_statement(account, options.from, options.to)),
+        None => exit_with_error(&format!("No transactions found for client {}", client_id)),
+    }
+}
+
+fn generate_files(options: &Options) -> std::io::Result<()> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(&options.file_path)?);
+    let balances: BTreeMap<u16, Snapshot> = generate(&options.generator, &mut writer)?;
+    writer.flush()?;
+    if let Some(expected_path) = &options.expected_path {
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(expected_path)?);
+        output_expected_balances(&balances, &mut writer)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn reconciliation(ledger: Ledger) {
+    let discrepancies: Vec<Discrepancy> = reconcile(&ledger);
+    output_reconciliation(&discrepancies);
+    if !discrepancies.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
 }
@@ -0,0 +1,247 @@
+#[cfg(test)]
+mod property_based_tests {
+    use std::collections::{BTreeSet, HashMap};
+    use proptest::prelude::*;
+    use crate::config::Config;
+    use crate::ledger::{Ledger, process_transaction, summarize_reader};
+    use crate::reconcile::reconcile;
+    use crate::utils::{Account, Snapshot, Transaction};
+
+    // Few clients and tx ids, so that disputes and the like usually find a deposit
+    const CLIENTS: u16 = 4;
+    const TX_IDS: u32 = 12;
+
+    #[derive(Clone, Debug)]
+    enum Row {
+        Deposit(u16, u32, f32),
+        Withdrawal(u16, u32, f32),
+        Dispute(u16, u32),
+        Resolve(u16, u32),
+        Chargeback(u16, u32),
+        // A row that fails to parse or is rejected without touching any balance
+        Invalid(String),
+    }
+
+    impl Row {
+        fn to_csv(&self) -> String {
+            match self {
+                Row::Deposit(client_id, tx, amount) => format!("deposit,{},{},{}", client_id, tx, amount),
+                Row::Withdrawal(client_id, tx, amount) => format!("withdrawal,{},{},{}", client_id, tx, amount),
+                Row::Dispute(client_id, tx) => format!("dispute,{},{},", client_id, tx),
+                Row::Resolve(client_id, tx) => format!("resolve,{},{},", client_id, tx),
+                Row::Chargeback(client_id, tx) => format!("chargeback,{},{},", client_id, tx),
+                Row::Invalid(row) => row.clone(),
+            }
+        }
+
+        fn to_transaction(&self) -> Option<Transaction> {
+            let (tx_type, client_id, tx, amount) = match self {
+                Row::Deposit(client_id, tx, amount) => ("deposit", client_id, tx, Some(*amount)),
+                Row::Withdrawal(client_id, tx, amount) => ("withdrawal", client_id, tx, Some(*amount)),
+                Row::Dispute(client_id, tx) => ("dispute", client_id, tx, None),
+                Row::Resolve(client_id, tx) => ("resolve", client_id, tx, None),
+                Row::Chargeback(client_id, tx) => ("chargeback", client_id, tx, None),
+                Row::Invalid(_) => return None,
+            };
+            Some(Transaction {
+                tx_type: tx_type.to_string(),
+                client_id: *client_id,
+                tx: *tx,
+                amount,
+                ..Default::default()
+            })
+        }
+
+        fn is_dispute_action(&self) -> bool {
+            matches!(self, Row::Dispute(..) | Row::Resolve(..) | Row::Chargeback(..))
+        }
+    }
+
+    // Multiples of a quarter are exact in f32, so balances can be compared exactly
+    fn amount() -> impl Strategy<Value = f32> {
+        (1u32..=400).prop_map(|quarters| quarters as f32 * 0.25)
+    }
+
+    fn invalid_row() -> impl Strategy<Value = String> {
+        (1..=CLIENTS, 1..=TX_IDS, 0usize..7).prop_map(|(client_id, tx, kind)| match kind {
+            0 => format!("deposit,abc,{},1.0", tx),
+            1 => format!("deposit,{},{},-5.0", client_id, tx),
+            2 => format!("withdrawal,{},{},", client_id, tx),
+            3 => format!("refund,{},{},1.0", client_id, tx),
+            4 => format!("deposit,70000,{},1.0", tx),
+            5 => format!("dispute,{},abc,", client_id),
+            _ => format!("deposit,{},{},1.0,not-a-date", client_id, tx),
+        })
+    }
+
+    fn row() -> impl Strategy<Value = Row> {
+        let client_id = 1..=CLIENTS;
+        let tx = 1..=TX_IDS;
+        prop_oneof![
+            4 => (client_id.clone(), tx.clone(), amount()).prop_map(|(c, t, a)| Row::Deposit(c, t, a)),
+            2 => (client_id.clone(), tx.clone(), amount()).prop_map(|(c, t, a)| Row::Withdrawal(c, t, a)),
+            2 => (client_id.clone(), tx.clone()).prop_map(|(c, t)| Row::Dispute(c, t)),
+            1 => (client_id.clone(), tx.clone()).prop_map(|(c, t)| Row::Resolve(c, t)),
+            1 => (client_id, tx).prop_map(|(c, t)| Row::Chargeback(c, t)),
+            1 => invalid_row().prop_map(Row::Invalid),
+        ]
+    }
+
+    fn rows() -> impl Strategy<Value = Vec<Row>> {
+        prop::collection::vec(row(), 0..60)
+    }
+
+    fn to_csv(rows: &[Row]) -> String {
+        let mut csv: String = "type,client,tx,amount\n".to_string();
+        for row in rows {
+            csv.push_str(&row.to_csv());
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn run(csv: &str) -> Ledger {
+        summarize_reader(csv.as_bytes(), Config::default())
+    }
+
+    fn balances(ledger: &Ledger) -> HashMap<u16, Snapshot> {
+        ledger.accounts.iter().map(|(client_id, account)| (*client_id, account.snapshot(&None))).collect()
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum DepositState {
+        Settled,
+        Disputed,
+        ChargedBack,
+    }
+
+    #[derive(Default)]
+    struct ReferenceAccount {
+        available: f32,
+        held: f32,
+        locked: bool,
+        chargebacks: u32,
+        deposits: HashMap<u32, (f32, DepositState)>,
+    }
+
+    // A straightforward model of the original rules, written without reference to the
+    // engine's own data structures
+    fn reference_model(rows: &[Row]) -> HashMap<u16, Snapshot> {
+        let mut accounts: HashMap<u16, ReferenceAccount> = HashMap::new();
+        for row in rows {
+            let Some(transaction) = row.to_transaction() else {
+                continue;
+            };
+            let account: &mut ReferenceAccount = accounts.entry(transaction.client_id).or_default();
+            if account.locked {
+                continue;
+            }
+            let deposit: Option<(f32, DepositState)> = account.deposits.get(&transaction.tx).copied();
+            match (row, deposit) {
+                (Row::Deposit(_, tx, amount), _) => {
+                    account.available += amount;
+                    account.deposits.entry(*tx).or_insert((*amount, DepositState::Settled));
+                },
+                (Row::Withdrawal(_, _, amount), _) if account.available >= *amount => account.available -= amount,
+                (Row::Dispute(_, tx), Some((amount, DepositState::Settled))) if account.available >= amount => {
+                    account.available -= amount;
+                    account.held += amount;
+                    account.deposits.insert(*tx, (amount, DepositState::Disputed));
+                },
+                (Row::Resolve(_, tx), Some((amount, DepositState::Disputed))) => {
+                    account.held -= amount;
+                    account.available += amount;
+                    account.deposits.insert(*tx, (amount, DepositState::Settled));
+                },
+                (Row::Chargeback(_, tx), Some((amount, DepositState::Disputed))) => {
+                    account.held -= amount;
+                    account.locked = true;
+                    account.chargebacks += 1;
+                    account.deposits.insert(*tx, (amount, DepositState::ChargedBack));
+                },
+                _ => {},
+            }
+        }
+        accounts.into_iter()
+            .map(|(client_id, account)| (client_id, Snapshot {
+                available: account.available,
+                held: account.held,
+                locked: account.locked,
+                chargebacks: account.chargebacks,
+                flagged: false,
+            }))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn money_is_conserved(rows in rows()) {
+            let csv: String = to_csv(&rows);
+            let ledger: Ledger = run(&csv);
+            let discrepancies = reconcile(&ledger);
+
+            prop_assert!(discrepancies.is_empty(), "{:?}\nfor input:\n{}", discrepancies, csv);
+            for (client_id, account) in ledger.accounts.iter() {
+                prop_assert!(account.held >= 0.0, "client {} held {}\nfor input:\n{}", client_id, account.held, csv);
+            }
+        }
+
+        #[test]
+        fn locked_accounts_never_change(rows in rows()) {
+            let mut ledger: Ledger = Ledger::new(Config::default());
+            let mut locked: HashMap<u16, Snapshot> = HashMap::new();
+
+            for (index, row) in rows.iter().enumerate() {
+                if let Some(mut transaction) = row.to_transaction() {
+                    let _ = process_transaction(&mut transaction, &mut ledger);
+                }
+                for (client_id, snapshot) in locked.iter() {
+                    let account: &Account = &ledger.accounts[client_id];
+                    prop_assert_eq!(
+                        account.snapshot(&None), *snapshot,
+                        "client {} changed after being locked\nfor input:\n{}", client_id, to_csv(&rows[..=index])
+                    );
+                }
+                for (client_id, account) in ledger.accounts.iter() {
+                    if account.locked {
+                        locked.entry(*client_id).or_insert(account.snapshot(&None));
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn duplicate_dispute_rows_are_ignored(rows in rows(), duplicates in prop::collection::vec(any::<bool>(), 60)) {
+            let mut duplicated_rows: Vec<Row> = vec![];
+            for (row, duplicate) in rows.iter().zip(duplicates) {
+                duplicated_rows.push(row.clone());
+                if duplicate && row.is_dispute_action() {
+                    duplicated_rows.push(row.clone());
+                }
+            }
+            let csv: String = to_csv(&rows);
+            let duplicated_csv: String = to_csv(&duplicated_rows);
+
+            prop_assert_eq!(
+                balances(&run(&csv)), balances(&run(&duplicated_csv)),
+                "duplicated rows changed the balances\nfor input:\n{}", duplicated_csv
+            );
+        }
+
+        #[test]
+        fn engine_agrees_with_reference_model(rows in rows()) {
+            let csv: String = to_csv(&rows);
+            let engine: HashMap<u16, Snapshot> = balances(&run(&csv));
+            let reference: HashMap<u16, Snapshot> = reference_model(&rows);
+            let client_ids: BTreeSet<&u16> = engine.keys().chain(reference.keys()).collect();
+
+            for client_id in client_ids {
+                prop_assert_eq!(
+                    engine.get(client_id).copied().unwrap_or_default(),
+                    reference.get(client_id).copied().unwrap_or_default(),
+                    "client {} differs from the reference model\nfor input:\n{}", client_id, csv
+                );
+            }
+        }
+    }
+}
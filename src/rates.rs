@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, HashMap};
+use csv::{ReaderBuilder, Reader, StringRecord};
+use crate::dates::parse_date;
+use crate::utils::{get_currency, get_float};
+
+// The most decimal places converted amounts may be rounded to. More would overflow
+// the scaled amount.
+pub const MAX_DECIMALS: u32 = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Rounding {
+    #[default]
+    Nearest,
+    Down,
+    Up,
+    Even,
+}
+
+impl Rounding {
+    pub fn parse(value: &str) -> Result<Rounding, ()> {
+        match value {
+            "nearest" => Ok(Rounding::Nearest),
+            "down" => Ok(Rounding::Down),
+            "up" => Ok(Rounding::Up),
+            "even" => Ok(Rounding::Even),
+            _ => Err(()),
+        }
+    }
+
+    pub fn apply(&self, amount: f32, decimals: u32) -> f32 {
+        let scale: f32 = 10f32.powi(decimals as i32);
+        let scaled: f32 = amount * scale;
+        let rounded: f32 = match self {
+            Rounding::Nearest => scaled.round(),
+            Rounding::Down => scaled.floor(),
+            Rounding::Up => scaled.ceil(),
+            Rounding::Even => scaled.round_ties_even(),
+        };
+        rounded / scale
+    }
+}
+
+// Rates for each currency pair, keyed by the day (counted from 1970-01-01) they
+// take effect
+#[derive(Default)]
+pub struct RateTable {
+    rates: HashMap<(String, String), BTreeMap<i64, f32>>,
+}
+
+impl RateTable {
+    pub fn insert(&mut self, from: &str, to: &str, day: i64, rate: f32) {
+        self.rates.entry((from.to_string(), to.to_string())).or_default().insert(day, rate);
+    }
+
+    // The latest rate on or before the given day. Without a day the latest rate in
+    // the table is used. A pair missing from the table falls back to the inverse of
+    // the opposite pair.
+    pub fn rate(&self, from: &str, to: &str, day: Option<i64>) -> Option<f32> {
+        if from == to {
+            return Some(1.0);
+        }
+        self.lookup(from, to, day)
+            .or_else(|| self.lookup(to, from, day).map(|rate| 1.0 / rate))
+    }
+
+    fn lookup(&self, from: &str, to: &str, day: Option<i64>) -> Option<f32> {
+        let rates: &BTreeMap<i64, f32> = self.rates.get(&(from.to_string(), to.to_string()))?;
+        match day {
+            Some(day) => rates.range(..=day).next_back().map(|(_, rate)| *rate),
+            None => rates.values().next_back().copied(),
+        }
+    }
+}
+
+// The rate table is read by header name and needs `date`, `from`, `to` and `rate`
+// columns. Rows that cannot be parsed or hold a zero rate are skipped.
+pub fn load_rates(file_path: &str) -> Result<RateTable, String> {
+    let mut rdr: Reader<std::fs::File> = ReaderBuilder::new()
+        .from_path(file_path)
+        .map_err(|err| format!("Unable to read rates file {}: {}", file_path, err))?;
+    let headers: StringRecord = rdr.headers()
+        .map_err(|err| format!("Unable to read rates file {}: {}", file_path, err))?
+        .clone();
+    let mut columns: Vec<usize> = vec![];
+    for name in ["date", "from", "to", "rate"] {
+        let index: usize = headers.iter().position(|header| header.trim() == name)
+            .ok_or(format!("Rates file {} has no {} column", file_path, name))?;
+        columns.push(index);
+    }
+    let mut table: RateTable = RateTable::default();
+
+    for result in rdr.records() {
+        let record: StringRecord = match result {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+        let day: i64 = match parse_date(record.get(columns[0]).unwrap_or_default()) {
+            Ok(day) => day,
+            Err(_) => continue,
+        };
+        let (Ok(Some(from)), Ok(Some(to))) = (get_currency(&record, columns[1]), get_currency(&record, columns[2])) else {
+            continue;
+        };
+        match get_float(&record, columns[3]) {
+            Ok(Some(rate)) if rate > 0.0 => table.insert(&from, &to, day, rate),
+            _ => continue,
+        }
+    }
+
+    Ok(table)
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_rate_lookup_by_date() {
+        let mut table: RateTable = RateTable::default();
+        table.insert("EUR", "GBP", 10, 0.8);
+        table.insert("EUR", "GBP", 20, 0.9);
+
+        assert_eq!(table.rate("EUR", "GBP", Some(9)), None);
+        assert_eq!(table.rate("EUR", "GBP", Some(10)), Some(0.8));
+        assert_eq!(table.rate("EUR", "GBP", Some(19)), Some(0.8));
+        assert_eq!(table.rate("EUR", "GBP", Some(25)), Some(0.9));
+        assert_eq!(table.rate("EUR", "GBP", None), Some(0.9));
+        assert_eq!(table.rate("GBP", "EUR", Some(10)), Some(1.25));
+        assert_eq!(table.rate("EUR", "USD", Some(10)), None);
+        assert_eq!(table.rate("USD", "USD", None), Some(1.0));
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(Rounding::Nearest.apply(1.23456, 2), 1.23);
+        assert_eq!(Rounding::Nearest.apply(1.235, 1), 1.2);
+        assert_eq!(Rounding::Down.apply(1.239, 2), 1.23);
+        assert_eq!(Rounding::Up.apply(1.231, 2), 1.24);
+        assert_eq!(Rounding::Even.apply(2.5, 0), 2.0);
+        assert_eq!(Rounding::Nearest.apply(2.5, 0), 3.0);
+    }
+
+    #[test]
+    fn test_load_rates() {
+        let path: std::path::PathBuf = std::env::temp_dir().join("rust_ledger_test_load_rates.csv");
+        std::fs::write(
+            &path,
+            "date,from,to,rate\n2026-09-30,eur,GBP,0.85\n2026-09-31,EUR,GBP,0.9\n2026-10-01,EUR,USD,0\n2026-10-01,EUR,GBP,0.86\n",
+        ).unwrap();
+        let table: RateTable = load_rates(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(table.rate("EUR", "GBP", Some(20_726)), Some(0.85));
+        assert_eq!(table.rate("EUR", "GBP", None), Some(0.86));
+        assert_eq!(table.rate("EUR", "USD", None), None);
+    }
+
+    #[test]
+    fn test_load_rates_missing_column() {
+        let path: std::path::PathBuf = std::env::temp_dir().join("rust_ledger_test_load_rates_no_rate.csv");
+        std::fs::write(&path, "date,from,to\n2026-09-30,EUR,GBP\n").unwrap();
+
+        assert!(load_rates(path.to_str().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use crate::journal::LedgerAccount;
+use crate::ledger::Ledger;
+use crate::utils::{Account, Snapshot, Transaction};
+
+// Balances are kept as f32, so sums taken in a different order may differ slightly
+const TOLERANCE: f32 = 0.0001;
+
+#[derive(Debug, PartialEq)]
+pub struct Discrepancy {
+    pub client_id: Option<u16>,
+    pub currency: Option<String>,
+    pub check: &'static str,
+    pub expected: f32,
+    pub actual: f32,
+}
+
+// Checks every client against their history and the journal, and the journal against
+// itself. Returns nothing when the ledger is consistent.
+pub fn reconcile(ledger: &Ledger) -> Vec<Discrepancy> {
+    let balances: BTreeMap<(LedgerAccount, Option<String>), f32> = ledger.journal.trial_balance();
+    let mut client_ids: Vec<&u16> = ledger.accounts.keys().collect();
+    client_ids.sort();
+    let mut discrepancies: Vec<Discrepancy> = vec![];
+
+    for client_id in client_ids {
+        let account: &Account = &ledger.accounts[client_id];
+        discrepancies.extend(check_account(*client_id, account));
+        discrepancies.extend(check_journal(*client_id, account, &balances));
+    }
+
+    let mut totals: BTreeMap<Option<String>, f32> = BTreeMap::new();
+    for ((_, currency), balance) in balances.iter() {
+        *totals.entry(currency.clone()).or_default() += balance;
+    }
+    for (currency, total) in totals {
+        push_if_different(&mut discrepancies, None, &currency, "trial_balance", 0.0, total);
+    }
+
+    discrepancies
+}
+
+// For every currency of the account:
+// - `funds`: deposits less withdrawals, chargebacks and captures, plus chargeback
+//   reversals and conversions in and less conversions out, equal the available and held funds. Entries pruned in
+//   bounded-memory mode count through their running total.
+// - `held`: held funds equal the disputed parts of deposits plus the open authorizations
+pub fn check_account(client_id: u16, account: &Account) -> Vec<Discrepancy> {
+    let mut currencies: Vec<Option<String>> = vec![None];
+    currencies.extend(account.currencies.keys().map(|currency| Some(currency.clone())));
+    let mut discrepancies: Vec<Discrepancy> = vec![];
+
+    for currency in currencies {
+        let mut net_funds: f32 = account.pruned_funds.get(&currency).copied().unwrap_or(0.0);
+        let mut expected_held: f32 = 0.0;
+        for transaction in account.transactions.iter().filter(|transaction| transaction.currency == currency) {
+            let amount: f32 = transaction.amount.unwrap_or(0.0);
+            net_funds += funds_movement(transaction);
+            if transaction.tx_type == "deposit" {
+                expected_held += transaction.disputed_amount;
+            } else if transaction.tx_type == "authorize" && transaction.authorized {
+                expected_held += amount;
+            }
+        }
+        let balance: Snapshot = account.snapshot(&currency);
+        push_if_different(&mut discrepancies, Some(client_id), &currency, "funds", net_funds, balance.available + balance.held);
+        push_if_different(&mut discrepancies, Some(client_id), &currency, "held", expected_held, balance.held);
+    }
+
+    discrepancies
+}
+
+// How much a history entry adds to or takes from the client's funds
+pub fn funds_movement(transaction: &Transaction) -> f32 {
+    let amount: f32 = transaction.amount.unwrap_or(0.0);
+    match transaction.tx_type.as_str() {
+        "deposit" | "convert_credit" | "chargeback_reversal" => amount,
+        "withdrawal" | "chargeback" | "capture" | "convert_debit" => -amount,
+        _ => 0.0,
+    }
+}
+
+// Checked in strict mode after every applied row, using the running history totals
+// so that the cost does not grow with the history:
+// - held is never negative
+// - available plus held equal the history-derived total
+// - the disputed and charged back parts of the deposit the row refers to are not
+//   negative and do not add up to more than the deposit
+pub fn check_invariants(account: &Account, transaction: &Transaction) -> Result<(), String> {
+    let row: String = format!(
+        "Invariant violated after row {} ({} client {} tx {})",
+        transaction.row, transaction.tx_type, transaction.client_id, transaction.tx
+    );
+    for (currency, expected_total) in account.history_totals.iter() {
+        let balance: Snapshot = account.snapshot(currency);
+        let currency: &str = currency.as_deref().unwrap_or("default");
+        if balance.held < -TOLERANCE {
+            return Err(format!("{}: {} held is negative at {:.4}", row, currency, balance.held));
+        }
+        let total: f32 = balance.available + balance.held;
+        if (expected_total - total).abs() > TOLERANCE * expected_total.abs().max(1.0) {
+            return Err(format!(
+                "{}: {} available {:.4} plus held {:.4} is {:.4} but the history gives {:.4}",
+                row, currency, balance.available, balance.held, total, expected_total
+            ));
+        }
+    }
+    if !["dispute", "resolve", "chargeback", "chargeback_reversal"].contains(&transaction.tx_type.as_str()) {
+        return Ok(());
+    }
+    let deposit: Option<&Transaction> = account.transactions.iter()
+        .find(|deposit| deposit.tx == transaction.tx && deposit.tx_type == "deposit");
+    if let Some(deposit) = deposit {
+        let amount: f32 = deposit.amount.unwrap_or(0.0);
+        let settled: f32 = deposit.disputed_amount + deposit.charged_back_amount;
+        if deposit.disputed_amount < -TOLERANCE || settled - amount > TOLERANCE * amount.max(1.0) {
+            return Err(format!(
+                "{}: deposit {} of {:.4} has {:.4} disputed and {:.4} charged back",
+                row, transaction.tx, amount, deposit.disputed_amount, deposit.charged_back_amount
+            ));
+        }
+    }
+    Ok(())
+}
+
+// The client's journal accounts are in credit by exactly their available and held funds
+fn check_journal(client_id: u16, account: &Account, balances: &BTreeMap<(LedgerAccount, Option<String>), f32>) -> Vec<Discrepancy> {
+    let mut discrepancies: Vec<Discrepancy> = vec![];
+    for (currency, balance) in account.snapshots() {
+        let journal_available: f32 = balances.get(&(LedgerAccount::ClientAvailable(client_id), currency.clone())).copied().unwrap_or(0.0);
+        let journal_held: f32 = balances.get(&(LedgerAccount::ClientHeld(client_id), currency.clone())).copied().unwrap_or(0.0);
+        push_if_different(&mut discrepancies, Some(client_id), &currency, "journal_available", balance.available, -journal_available);
+        push_if_different(&mut discrepancies, Some(client_id), &currency, "journal_held", balance.held, -journal_held);
+    }
+    discrepancies
+}
+
+fn push_if_different(
+    discrepancies: &mut Vec<Discrepancy>,
+    client_id: Option<u16>,
+    currency: &Option<String>,
+    check: &'static str,
+    expected: f32,
+    actual: f32,
+) {
+    if (expected - actual).abs() > TOLERANCE * expected.abs().max(1.0) {
+        discrepancies.push(Discrepancy {
+            client_id,
+            currency: currency.clone(),
+            check,
+            expected,
+            actual,
+        });
+    }
+}
+
+pub fn output_reconciliation(discrepancies: &[Discrepancy]) {
+    println!("client,currency,check,expected,actual");
+    for discrepancy in discrepancies {
+        println!(
+            "{},{},{},{:.4},{:.4}",
+            discrepancy.client_id.map(|client_id| client_id.to_string()).unwrap_or_default(),
+            discrepancy.currency.as_deref().unwrap_or_default(),
+            discrepancy.check,
+            discrepancy.expected,
+            discrepancy.actual
+        );
+    }
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+    use crate::utils::Transaction;
+
+    fn history_entry(tx_type: &str, tx: u32, amount: f32) -> Transaction {
+        Transaction {
+            tx_type: tx_type.to_string(),
+            client_id: 1,
+            tx,
+            amount: Some(amount),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_account_consistent() {
+        let account: Account = Account {
+            available: 40.0,
+            held: 30.0,
+            transactions: vec![
+                Transaction { disputed: true, disputed_amount: 30.0, ..history_entry("deposit", 1, 30.0) },
+                history_entry("deposit", 2, 60.0),
+                history_entry("withdrawal", 3, 20.0),
+                history_entry("dispute", 1, 30.0),
+            ],
+            ..Default::default()
+        };
+
+        assert!(check_account(1, &account).is_empty());
+    }
+
+    #[test]
+    fn test_check_account_reports_failures() {
+        let account: Account = Account {
+            available: 50.0,
+            held: 10.0,
+            transactions: vec![history_entry("deposit", 1, 50.0)],
+            ..Default::default()
+        };
+        let discrepancies: Vec<Discrepancy> = check_account(7, &account);
+
+        assert_eq!(discrepancies.len(), 2);
+        assert_eq!(discrepancies[0], Discrepancy {
+            client_id: Some(7),
+            currency: None,
+            check: "funds",
+            expected: 50.0,
+            actual: 60.0,
+        });
+        assert_eq!(discrepancies[1].check, "held");
+        assert_eq!(discrepancies[1].expected, 0.0);
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let transaction: Transaction = Transaction { row: 4, ..history_entry("dispute", 1, 30.0) };
+        let mut account: Account = Account {
+            available: 20.0,
+            held: 30.0,
+            transactions: vec![Transaction { disputed: true, disputed_amount: 30.0, ..history_entry("deposit", 1, 50.0) }],
+            history_totals: BTreeMap::from([(None, 50.0)]),
+            ..Default::default()
+        };
+        assert_eq!(check_invariants(&account, &transaction), Ok(()));
+
+        account.available = 25.0;
+        assert_eq!(
+            check_invariants(&account, &transaction),
+            Err("Invariant violated after row 4 (dispute client 1 tx 1): default available 25.0000 plus held 30.0000 is 55.0000 but the history gives 50.0000".to_string())
+        );
+
+        account.available = 80.0;
+        account.held = -30.0;
+        assert!(check_invariants(&account, &transaction).unwrap_err().contains("held is negative"));
+
+        account.available = 20.0;
+        account.held = 30.0;
+        account.transactions[0].charged_back = true;
+        account.transactions[0].charged_back_amount = 30.0;
+        assert_eq!(
+            check_invariants(&account, &transaction),
+            Err("Invariant violated after row 4 (dispute client 1 tx 1): deposit 1 of 50.0000 has 30.0000 disputed and 30.0000 charged back".to_string())
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use crate::dates::{format_timestamp, SECONDS_PER_DAY};
+use crate::disputes::{DisputeRecord, DisputeStatus};
+use crate::utils::{Account, Transaction};
+
+pub struct StatementLine {
+    pub timestamp: Option<i64>,
+    pub tx_type: String,
+    pub tx: u32,
+    pub amount: Option<f32>,
+    pub currency: Option<String>,
+    pub available: f32,
+    pub held: f32,
+    pub dispute_status: String,
+}
+
+// Lists the account's history in the order it was applied, with the balances after
+// each line in that line's currency. `from` and `to` are inclusive days counted from 1970-01-01; when either
+// is given, lines without a timestamp are left out.
+pub fn account_statement(account: &Account, from: Option<i64>, to: Option<i64>) -> Vec<StatementLine> {
+    let start: Option<i64> = from.map(|day| day * SECONDS_PER_DAY);
+    let end: Option<i64> = to.map(|day| (day + 1) * SECONDS_PER_DAY);
+    let mut lines: Vec<StatementLine> = vec![];
+
+    for transaction in account.transactions.iter() {
+        if (start.is_some() || end.is_some()) && !is_within(transaction.timestamp, start, end) {
+            continue;
+        }
+        lines.push(StatementLine {
+            timestamp: transaction.timestamp,
+            tx_type: transaction.tx_type.clone(),
+            tx: transaction.tx,
+            amount: transaction.amount,
+            currency: transaction.currency.clone(),
+            available: transaction.snapshot.available,
+            held: transaction.snapshot.held,
+            dispute_status: dispute_status(transaction, &account.disputes).to_string(),
+        });
+    }
+
+    lines
+}
+
+fn is_within(timestamp: Option<i64>, start: Option<i64>, end: Option<i64>) -> bool {
+    match timestamp {
+        Some(timestamp) => start.is_none_or(|start| timestamp >= start) && end.is_none_or(|end| timestamp < end),
+        None => false,
+    }
+}
+
+// The current dispute status of a deposit, taken from its dispute record so that a
+// deposit with a part still held reads as disputed after a partial resolve
+fn dispute_status(transaction: &Transaction, disputes: &BTreeMap<u32, DisputeRecord>) -> &'static str {
+    if transaction.tx_type != "deposit" {
+        return "";
+    }
+    match disputes.get(&transaction.tx).map(|record| record.status) {
+        Some(DisputeStatus::Open) => "disputed",
+        Some(status) => status.name(),
+        None => "",
+    }
+}
+
+pub fn output_statement(lines: &[StatementLine]) {
+    println!("timestamp,type,tx,amount,currency,available,held,dispute_status");
+    for line in lines {
+        let timestamp: String = line.timestamp.map(format_timestamp).unwrap_or_default();
+        let amount: String = line.amount.map(|amount| format!("{:.4}", amount)).unwrap_or_default();
+        println!(
+            "{},{},{},{},{},{:.4},{:.4},{}",
+            timestamp,
+            line.tx_type,
+            line.tx,
+            amount,
+            line.currency.as_deref().unwrap_or_default(),
+            line.available,
+            line.held,
+            line.dispute_status
+        );
+    }
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+    use crate::utils::Snapshot;
+
+    fn history_entry(tx_type: &str, tx: u32, amount: f32, timestamp: Option<i64>, available: f32, held: f32) -> Transaction {
+        Transaction {
+            tx_type: tx_type.to_string(),
+            client_id: 1,
+            tx,
+            amount: Some(amount),
+            timestamp,
+            snapshot: Snapshot {
+                available,
+                held,
+                locked: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn dispute_record(tx: u32, status: DisputeStatus) -> DisputeRecord {
+        DisputeRecord {
+            id: tx as u64,
+            client_id: 1,
+            tx,
+            currency: None,
+            status,
+            redisputes: 0,
+            events: vec![],
+        }
+    }
+
+    fn example_account() -> Account {
+        Account {
+            available: 70.0,
+            held: 50.0,
+            transactions: vec![
+                history_entry("deposit", 1, 100.0, Some(0), 100.0, 0.0),
+                history_entry("deposit", 2, 50.0, Some(SECONDS_PER_DAY), 150.0, 0.0),
+                history_entry("withdrawal", 3, 30.0, None, 120.0, 0.0),
+                history_entry("dispute", 2, 50.0, Some(2 * SECONDS_PER_DAY), 70.0, 50.0),
+                history_entry("dispute", 1, 100.0, Some(2 * SECONDS_PER_DAY), -30.0, 150.0),
+                history_entry("resolve", 1, 100.0, Some(3 * SECONDS_PER_DAY), 70.0, 50.0),
+            ],
+            disputes: BTreeMap::from([
+                (1, dispute_record(1, DisputeStatus::Resolved)),
+                (2, dispute_record(2, DisputeStatus::Open)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_account_statement() {
+        let lines: Vec<StatementLine> = account_statement(&example_account(), None, None);
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0].tx_type, "deposit");
+        assert_eq!(lines[0].dispute_status, "resolved");
+        assert_eq!(lines[1].dispute_status, "disputed");
+        assert_eq!(lines[2].dispute_status, "");
+        assert_eq!(lines[3].available, 70.0);
+        assert_eq!(lines[3].held, 50.0);
+        assert_eq!(lines[3].amount, Some(50.0));
+    }
+
+    #[test]
+    fn test_account_statement_date_range() {
+        let lines: Vec<StatementLine> = account_statement(&example_account(), Some(1), Some(2));
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].tx, 2);
+        assert_eq!(lines[2].tx_type, "dispute");
+        assert_eq!(lines[2].tx, 1);
+    }
+
+    #[test]
+    fn test_account_statement_open_ended_range() {
+        assert_eq!(account_statement(&example_account(), Some(3), None).len(), 1);
+        assert_eq!(account_statement(&example_account(), None, Some(0)).len(), 1);
+    }
+}
@@ -1,8 +1,18 @@
 #[cfg(test)]
+// The original tests compare booleans with `assert_eq!`
+#[allow(clippy::bool_assert_comparison)]
 mod functional_tests {
-    use crate::*;
-    use crate::utils::{Account, Transaction};
-    use crate::ledger::process_transaction;
+    use std::collections::{BTreeMap, HashMap};
+    use crate::config::{AccountLimits, Config, InputFormat, LockPolicy};
+    use csv::StringRecord;
+    use crate::utils::{Account, Rejection, Snapshot, Transaction, get_float};
+    use crate::journal::LedgerAccount;
+    use crate::reconcile::reconcile;
+    use crate::ledger::{Ledger, process_transaction, summarize_accounts, summarize_into, summarize_reader};
+    use crate::statement::{account_statement, StatementLine};
+    use crate::generator::{GeneratorOptions, generate};
+    use crate::disputes::{DisputeEventKind, DisputeRecord, DisputeStatus};
+    use crate::dialects::Dialect;
 
     fn check_account(account: &Account, available: f32, held: f32, locked: bool) {
         assert_eq!(account.available, available);
@@ -10,9 +20,9 @@ mod functional_tests {
         assert_eq!(account.locked, locked);
     }
 
-    fn process_multiple_transactions(transactions: &mut Vec<Transaction>, accounts: &mut HashMap<u16, Account>){
+    fn process_multiple_transactions(transactions: &mut [Transaction], ledger: &mut Ledger){
         for transaction in transactions.iter_mut() {
-            process_transaction(transaction, accounts);
+            let _ = process_transaction(transaction, ledger);
         }
     }
 
@@ -25,6 +35,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             crate::utils::Transaction {
                 tx_type: "withdrawal".to_string(),
@@ -32,11 +43,12 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 50.0, 0.0, false);
     }
@@ -50,6 +62,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "withdrawal".to_string(),
@@ -57,11 +70,12 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(200.0),
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 100.0, 0.0, false);
     }
@@ -75,6 +89,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "withdrawal".to_string(),
@@ -82,6 +97,7 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -89,11 +105,12 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 50.0, 0.0, false);
     }
@@ -107,6 +124,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -114,6 +132,7 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -121,11 +140,12 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 0.0, 100.0, false);
     }
@@ -139,6 +159,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -146,11 +167,12 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 100.0, 0.0, false);
     }
@@ -164,6 +186,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "resolve".to_string(),
@@ -171,11 +194,12 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 100.0, 0.0, false);
     }
@@ -189,6 +213,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "resolve".to_string(),
@@ -196,11 +221,12 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 100.0, 0.0, false);
     }
@@ -214,6 +240,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "chargeback".to_string(),
@@ -221,11 +248,12 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 100.0, 0.0, false);
     }
@@ -239,6 +267,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "chargeback".to_string(),
@@ -246,11 +275,12 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 100.0, 0.0, false);
     }
@@ -264,6 +294,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "withdrawal".to_string(),
@@ -271,6 +302,7 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -278,11 +310,12 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 50.0, 0.0, false);
     }
@@ -296,6 +329,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -303,6 +337,7 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -310,6 +345,7 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -317,11 +353,12 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 0.0, 150.0, false);
     }
@@ -335,6 +372,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -342,6 +380,7 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -349,6 +388,7 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -356,6 +396,7 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "resolve".to_string(),
@@ -363,6 +404,7 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "resolve".to_string(),
@@ -370,11 +412,12 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 150.0, 0.0, false);
     }
@@ -388,6 +431,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -395,6 +439,7 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -402,6 +447,7 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -409,6 +455,7 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "resolve".to_string(),
@@ -416,6 +463,7 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "chargeback".to_string(),
@@ -423,11 +471,12 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 100.0, 0.0, true);
     }
@@ -441,6 +490,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -448,6 +498,7 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "chargeback".to_string(),
@@ -455,6 +506,7 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -462,12 +514,13 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(1000.0),
                 disputed: false,
+                ..Default::default()
             },
 
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 0.0, 0.0, true);
         assert_eq!(account.transactions.len(), 3);
@@ -482,6 +535,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -489,6 +543,7 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(200.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "withdrawal".to_string(),
@@ -496,6 +551,7 @@ mod functional_tests {
                 tx: 3,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "withdrawal".to_string(),
@@ -503,6 +559,7 @@ mod functional_tests {
                 tx: 4,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -510,6 +567,7 @@ mod functional_tests {
                 tx: 5,
                 amount: Some(25.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -517,6 +575,7 @@ mod functional_tests {
                 tx: 6,
                 amount: Some(50.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -524,6 +583,7 @@ mod functional_tests {
                 tx: 5,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "dispute".to_string(),
@@ -531,6 +591,7 @@ mod functional_tests {
                 tx: 6,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "resolve".to_string(),
@@ -538,6 +599,7 @@ mod functional_tests {
                 tx: 5,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "chargeback".to_string(),
@@ -545,12 +607,13 @@ mod functional_tests {
                 tx: 6,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account1: &Account = accounts.get(&1).unwrap();
-        let account2: &Account = accounts.get(&2).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account1: &Account = ledger.accounts.get(&1).unwrap();
+        let account2: &Account = ledger.accounts.get(&2).unwrap();
 
         check_account(account1, 75.0, 0.0, false);
         check_account(account2, 100.0, 0.0, true);
@@ -565,6 +628,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.5555),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "deposit".to_string(),
@@ -572,6 +636,7 @@ mod functional_tests {
                 tx: 2,
                 amount: Some(20.2222),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "withdrawal".to_string(),
@@ -579,11 +644,12 @@ mod functional_tests {
                 tx: 3,
                 amount: Some(50.1111),
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         assert_eq!((account.available * 10000.0).round() / 10000.0, 70.6666);
         assert_eq!(account.held, 0.0);
@@ -599,11 +665,12 @@ mod functional_tests {
                 tx: 1,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 0.0, 0.0, false);
     }
@@ -617,6 +684,7 @@ mod functional_tests {
                 tx: 1,
                 amount: Some(100.0),
                 disputed: false,
+                ..Default::default()
             },
             Transaction {
                 tx_type: "withdrawal".to_string(),
@@ -624,12 +692,1299 @@ mod functional_tests {
                 tx: 2,
                 amount: None,
                 disputed: false,
+                ..Default::default()
             },
         ];
-        let accounts: &mut HashMap<u16, Account> = &mut HashMap::new();
-        process_multiple_transactions(transactions, accounts);
-        let account: &Account = accounts.get(&1).unwrap();
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
 
         check_account(account, 100.0, 0.0, false);
     }
+
+    fn overdraft_ledger(client_id: u16, overdraft: f32) -> Ledger {
+        let mut config: Config = Config::default();
+        config.limits.insert(client_id, AccountLimits { overdraft, ..Default::default() });
+        Ledger::new(config)
+    }
+
+    #[test]
+    fn test_withdrawal_within_overdraft_limit() {
+        let transactions: &mut Vec<Transaction> = &mut vec![
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 1,
+                amount: Some(100.0),
+                disputed: false,
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "withdrawal".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(150.0),
+                disputed: false,
+                ..Default::default()
+            },
+        ];
+        let ledger: &mut Ledger = &mut overdraft_ledger(1, 50.0);
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, -50.0, 0.0, false);
+    }
+
+    #[test]
+    fn test_withdrawal_beyond_overdraft_limit() {
+        let transactions: &mut Vec<Transaction> = &mut vec![
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 1,
+                amount: Some(100.0),
+                disputed: false,
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "withdrawal".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(150.0),
+                disputed: false,
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "withdrawal".to_string(),
+                client_id: 2,
+                tx: 3,
+                amount: Some(10.0),
+                disputed: false,
+                ..Default::default()
+            },
+        ];
+        let ledger: &mut Ledger = &mut overdraft_ledger(1, 49.0);
+        process_multiple_transactions(transactions, ledger);
+        let account1: &Account = ledger.accounts.get(&1).unwrap();
+        let account2: &Account = ledger.accounts.get(&2).unwrap();
+
+        check_account(account1, 100.0, 0.0, false);
+        check_account(account2, 0.0, 0.0, false);
+    }
+
+    fn authorization_ledger(expiry_rows: u64) -> Ledger {
+        let config: Config = Config {
+            authorization_expiry_rows: Some(expiry_rows),
+            ..Default::default()
+        };
+        Ledger::new(config)
+    }
+
+    #[test]
+    fn test_authorize_then_capture() {
+        let transactions: &mut Vec<Transaction> = &mut vec![
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 1,
+                amount: Some(100.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "authorize".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(40.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "capture".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: None,
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "release".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: None,
+                ..Default::default()
+            },
+        ];
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 60.0, 0.0, false);
+        assert_eq!(account.transactions.len(), 3);
+    }
+
+    #[test]
+    fn test_capture_more_than_authorized() {
+        let transactions: &mut Vec<Transaction> = &mut vec![
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 1,
+                amount: Some(100.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "authorize".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(40.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "capture".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(50.0),
+                ..Default::default()
+            },
+        ];
+        let ledger: &mut Ledger = &mut Ledger::default();
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 60.0, 40.0, false);
+    }
+
+    #[test]
+    fn test_authorization_expires_after_rows() {
+        let transactions: &mut Vec<Transaction> = &mut vec![
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 1,
+                amount: Some(100.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "authorize".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(40.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 2,
+                tx: 3,
+                amount: Some(10.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "capture".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: None,
+                ..Default::default()
+            },
+        ];
+        let ledger: &mut Ledger = &mut authorization_ledger(1);
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 100.0, 0.0, false);
+        assert_eq!(account.transactions.last().unwrap().tx_type, "expire".to_string());
+        assert!(ledger.pending_authorizations.is_empty());
+    }
+
+    #[test]
+    fn test_authorization_captured_within_expiry() {
+        let transactions: &mut Vec<Transaction> = &mut vec![
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 1,
+                amount: Some(100.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "authorize".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(40.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 2,
+                tx: 3,
+                amount: Some(10.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "capture".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: None,
+                ..Default::default()
+            },
+        ];
+        let ledger: &mut Ledger = &mut authorization_ledger(2);
+        process_multiple_transactions(transactions, ledger);
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 60.0, 0.0, false);
+    }
+
+    fn withdrawal(client_id: u16, tx: u32, amount: f32) -> Transaction {
+        Transaction {
+            tx_type: "withdrawal".to_string(),
+            client_id,
+            tx,
+            amount: Some(amount),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_velocity_limits_reject_withdrawals() {
+        let mut config: Config = Config::default();
+        config.limits.insert(1, AccountLimits {
+            max_withdrawal: Some(100.0),
+            max_daily_withdrawal: Some(150.0),
+            max_withdrawals: Some(2),
+            withdrawal_window: Some(3),
+            ..Default::default()
+        });
+        let ledger: &mut Ledger = &mut Ledger::new(config);
+        let deposit: &mut Transaction = &mut Transaction {
+            tx_type: "deposit".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: Some(1000.0),
+            ..Default::default()
+        };
+
+        assert_eq!(process_transaction(deposit, ledger), Ok(()));
+        assert_eq!(process_transaction(&mut withdrawal(1, 2, 101.0), ledger), Err(Rejection::MaxWithdrawalExceeded));
+        assert_eq!(process_transaction(&mut withdrawal(1, 3, 60.0), ledger), Ok(()));
+        assert_eq!(process_transaction(&mut withdrawal(1, 4, 60.0), ledger), Ok(()));
+        assert_eq!(process_transaction(&mut withdrawal(1, 5, 10.0), ledger), Err(Rejection::WithdrawalCountLimitExceeded));
+        assert_eq!(process_transaction(&mut withdrawal(1, 6, 40.0), ledger), Err(Rejection::DailyWithdrawalLimitExceeded));
+        assert_eq!(process_transaction(&mut withdrawal(1, 7, 30.0), ledger), Ok(()));
+
+        check_account(ledger.accounts.get(&1).unwrap(), 850.0, 0.0, false);
+    }
+
+    #[test]
+    fn test_rejection_reasons() {
+        let ledger: &mut Ledger = &mut Ledger::default();
+        let mut empty_deposit: Transaction = Transaction {
+            tx_type: "deposit".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: None,
+            ..Default::default()
+        };
+        let mut dispute: Transaction = Transaction {
+            tx_type: "dispute".to_string(),
+            client_id: 1,
+            tx: 1,
+            amount: None,
+            ..Default::default()
+        };
+
+        assert_eq!(process_transaction(&mut empty_deposit, ledger), Err(Rejection::MissingAmount));
+        assert_eq!(process_transaction(&mut withdrawal(1, 2, 10.0), ledger), Err(Rejection::InsufficientFunds));
+        assert_eq!(process_transaction(&mut dispute, ledger), Err(Rejection::NoMatchingTransaction));
+    }
+
+    fn write_transactions_file(name: &str, contents: &str) -> String {
+        let path: std::path::PathBuf = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_timestamps_are_parsed() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_timestamps.csv",
+            "type,client,tx,amount,timestamp\n\
+            deposit,1,1,100.0,2026-09-30T10:00:00Z\n\
+            deposit,1,2,50.0,\n\
+            deposit,1,3,25.0,yesterday\n\
+            deposit,1,4,10.0\n",
+        );
+        let ledger: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 160.0, 0.0, false);
+        assert_eq!(account.transactions[0].timestamp, Some(1_790_762_400));
+        assert_eq!(account.transactions[1].timestamp, None);
+        assert_eq!(account.transactions.len(), 3);
+    }
+
+    #[test]
+    fn test_out_of_order_rows_are_resorted_within_window() {
+        let contents: &str = "type,client,tx,amount,timestamp\n\
+            withdrawal,1,2,80.0,2026-09-30T11:00:00Z\n\
+            deposit,1,1,100.0,2026-09-30T10:00:00Z\n";
+        let file_path: String = write_transactions_file("rust_ledger_test_reorder.csv", contents);
+
+        let in_file_order: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+        check_account(in_file_order.accounts.get(&1).unwrap(), 100.0, 0.0, false);
+
+        let config: Config = Config {
+            reorder_window: 1,
+            ..Default::default()
+        };
+        let resorted: Ledger = summarize_accounts(&file_path, config).unwrap();
+        let account: &Account = resorted.accounts.get(&1).unwrap();
+        check_account(account, 20.0, 0.0, false);
+        assert_eq!(account.transactions[0].tx, 1);
+        assert_eq!(account.transactions[1].row, 2);
+    }
+
+    #[test]
+    fn test_authorization_expires_after_time_limit() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_authorization_expiry_secs.csv",
+            "type,client,tx,amount,timestamp\n\
+            deposit,1,1,100.0,2026-09-30T10:00:00Z\n\
+            authorize,1,2,40.0,2026-09-30T10:00:00Z\n\
+            authorize,1,3,30.0,2026-09-30T10:30:00Z\n\
+            capture,1,2,,2026-09-30T11:00:01Z\n\
+            capture,1,3,,2026-09-30T11:00:01Z\n",
+        );
+        let config: Config = Config {
+            authorization_expiry_secs: Some(3600),
+            ..Default::default()
+        };
+        let ledger: Ledger = summarize_accounts(&file_path, config).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 70.0, 0.0, false);
+        assert_eq!(ledger.rejections.len(), 1);
+        assert_eq!(ledger.rejections[0].1, Rejection::NoMatchingTransaction);
+    }
+
+    #[test]
+    fn test_largest_expiry_and_window_do_not_overflow() {
+        let contents: &str = "type,client,tx,amount,timestamp\n\
+            deposit,1,1,100.0,2026-09-30T10:00:00Z\n\
+            authorize,1,2,40.0,2026-09-30T10:00:00Z\n\
+            withdrawal,1,3,10.0,2026-09-30T11:00:00Z\n\
+            withdrawal,1,4,10.0,2026-09-30T12:00:00Z\n";
+        let mut config: Config = Config {
+            authorization_expiry_rows: Some(u64::MAX),
+            authorization_expiry_secs: Some(i64::MAX),
+            ..Default::default()
+        };
+        config.limits.insert(1, AccountLimits {
+            max_withdrawals: Some(1),
+            withdrawal_window: Some(u64::MAX),
+            ..Default::default()
+        });
+        let ledger: Ledger = summarize_reader(contents.as_bytes(), config);
+
+        check_account(ledger.accounts.get(&1).unwrap(), 50.0, 40.0, false);
+        assert_eq!(ledger.rejections[0].1, Rejection::WithdrawalCountLimitExceeded);
+    }
+
+    #[test]
+    fn test_daily_withdrawal_limit_resets_each_day() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_daily_limit.csv",
+            "type,client,tx,amount,timestamp\n\
+            deposit,1,1,500.0,2026-09-29T10:00:00Z\n\
+            withdrawal,1,2,100.0,2026-09-29T11:00:00Z\n\
+            withdrawal,1,3,100.0,2026-09-29T23:59:59Z\n\
+            withdrawal,1,4,100.0,2026-09-30T00:00:00Z\n",
+        );
+        let mut config: Config = Config::default();
+        config.limits.insert(1, AccountLimits {
+            max_daily_withdrawal: Some(150.0),
+            ..Default::default()
+        });
+        let ledger: Ledger = summarize_accounts(&file_path, config).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 300.0, 0.0, false);
+        assert_eq!(ledger.rejections[0].1, Rejection::DailyWithdrawalLimitExceeded);
+    }
+
+    #[test]
+    fn test_balances_as_of_date() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_balances_as_of.csv",
+            "type,client,tx,amount,timestamp\n\
+            deposit,1,1,100.0,2026-09-29T10:00:00Z\n\
+            deposit,2,2,70.0,2026-09-29T11:00:00Z\n\
+            dispute,1,1,,2026-09-30T09:00:00Z\n\
+            deposit,1,3,20.0,2026-09-30T23:59:59Z\n\
+            chargeback,1,1,,2026-10-01T00:00:00Z\n\
+            deposit,3,4,5.0,2026-10-01T01:00:00Z\n\
+            withdrawal,2,5,10.0\n",
+        );
+        let ledger: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+
+        let september_29: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_725);
+        assert_eq!(september_29.len(), 2);
+        assert_eq!(september_29.get(&(1, None)).unwrap(), &Snapshot { available: 100.0, held: 0.0, locked: false, ..Default::default() });
+        assert_eq!(september_29.get(&(2, None)).unwrap(), &Snapshot { available: 70.0, held: 0.0, locked: false, ..Default::default() });
+
+        let september_30: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_726);
+        assert_eq!(september_30.get(&(1, None)).unwrap(), &Snapshot { available: 20.0, held: 100.0, locked: false, ..Default::default() });
+
+        let october_1: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_727);
+        assert_eq!(october_1.len(), 3);
+        assert_eq!(october_1.get(&(1, None)).unwrap(), &Snapshot { available: 20.0, held: 0.0, locked: true, chargebacks: 1, flagged: false });
+        assert_eq!(october_1.get(&(2, None)).unwrap(), &Snapshot { available: 70.0, held: 0.0, locked: false, ..Default::default() });
+
+        assert!(ledger.balances_as_of(20_724).is_empty());
+    }
+
+    #[test]
+    fn test_balances_as_of_follows_timestamps() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_balances_as_of_order.csv",
+            "type,client,tx,amount,timestamp\n\
+            deposit,1,1,100.0,2026-09-29T10:00:00Z\n\
+            deposit,1,2,20.0,2026-09-30T12:00:00Z\n\
+            deposit,1,3,5.0,2026-09-29T18:00:00Z\n",
+        );
+        let ledger: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+
+        let september_29: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_725);
+        assert_eq!(september_29.get(&(1, None)).unwrap().available, 105.0);
+        let september_30: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_726);
+        assert_eq!(september_30.get(&(1, None)).unwrap().available, 125.0);
+    }
+
+    #[test]
+    fn test_balances_as_of_out_of_order_timestamps() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_balances_as_of_out_of_order.csv",
+            "type,client,tx,amount,timestamp\n\
+            deposit,1,1,100,2026-10-02\n\
+            deposit,1,2,50,2026-09-30\n\
+            dispute,1,1,,2026-10-03\n\
+            dispute,1,2,,2026-10-01\n\
+            deposit,1,3,7\n",
+        );
+        let ledger: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+
+        let september_30: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_726);
+        assert_eq!(september_30.get(&(1, None)).unwrap(), &Snapshot { available: 50.0, held: 0.0, ..Default::default() });
+        let october_1: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_727);
+        assert_eq!(october_1.get(&(1, None)).unwrap(), &Snapshot { available: 0.0, held: 50.0, ..Default::default() });
+        let october_2: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_728);
+        assert_eq!(october_2.get(&(1, None)).unwrap(), &Snapshot { available: 100.0, held: 50.0, ..Default::default() });
+        // The undated deposit is left out of every date
+        let october_3: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_729);
+        assert_eq!(october_3.get(&(1, None)).unwrap(), &Snapshot { available: 0.0, held: 150.0, ..Default::default() });
+    }
+
+    #[test]
+    fn test_balances_as_of_locks_every_currency() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_balances_as_of_locked.csv",
+            "type,client,tx,amount,timestamp,currency\n\
+            deposit,1,1,50.0,2026-09-29,GBP\n\
+            deposit,1,2,100.0,2026-09-29,EUR\n\
+            dispute,1,2,,2026-09-30\n\
+            chargeback,1,2,,2026-09-30\n",
+        );
+        let ledger: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+
+        let september_29: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_725);
+        assert_eq!(september_29.get(&(1, Some("GBP".to_string()))).unwrap().locked, false);
+        let september_30: HashMap<(u16, Option<String>), Snapshot> = ledger.balances_as_of(20_726);
+        assert_eq!(september_30.get(&(1, Some("EUR".to_string()))).unwrap(), &Snapshot { available: 0.0, held: 0.0, locked: true, chargebacks: 1, flagged: false });
+        assert_eq!(september_30.get(&(1, Some("GBP".to_string()))).unwrap(), &Snapshot { available: 50.0, held: 0.0, locked: true, chargebacks: 1, flagged: false });
+    }
+
+    #[test]
+    fn test_statement_for_client() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_statement.csv",
+            "type,client,tx,amount,timestamp\n\
+            deposit,1,1,100.0,2026-09-29T10:00:00Z\n\
+            deposit,2,2,70.0,2026-09-29T11:00:00Z\n\
+            deposit,1,3,40.0,2026-09-30T09:00:00Z\n\
+            dispute,1,1,,2026-09-30T10:00:00Z\n\
+            resolve,1,1,,2026-10-01T10:00:00Z\n",
+        );
+        let ledger: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        let lines: Vec<StatementLine> = account_statement(account, None, None);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].dispute_status, "resolved");
+        assert_eq!(lines[2].tx_type, "dispute");
+        assert_eq!(lines[2].amount, Some(100.0));
+        assert_eq!((lines[2].available, lines[2].held), (40.0, 100.0));
+        assert_eq!((lines[3].available, lines[3].held), (140.0, 0.0));
+
+        let september_30: Vec<StatementLine> = account_statement(account, Some(20_726), Some(20_726));
+        assert_eq!(september_30.len(), 2);
+        assert_eq!(september_30[0].tx, 3);
+    }
+
+    #[test]
+    fn test_statement_after_partial_resolve() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_statement_partial_resolve.csv",
+            "type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            dispute,1,1,30.0\n\
+            dispute,1,1,20.0\n\
+            resolve,1,1,20.0\n",
+        );
+        let ledger: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        let lines: Vec<StatementLine> = account_statement(account, None, None);
+        assert_eq!(lines[0].dispute_status, "disputed");
+        assert_eq!((lines[3].available, lines[3].held), (70.0, 30.0));
+    }
+
+    #[test]
+    fn test_balances_are_kept_per_currency() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_currencies.csv",
+            "type,client,tx,amount,timestamp,currency\n\
+            deposit,1,1,100.0,,EUR\n\
+            deposit,1,2,50.0,,gbp\n\
+            deposit,1,3,10.0\n\
+            withdrawal,1,4,60.0,,GBP\n\
+            withdrawal,1,5,20.0,,GBP\n\
+            dispute,1,2,,,EUR\n\
+            dispute,1,1\n\
+            deposit,2,6,5.0,,US1\n",
+        );
+        let ledger: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+        let snapshots: Vec<(Option<String>, Snapshot)> = account.snapshots();
+
+        check_account(account, 10.0, 0.0, false);
+        assert_eq!(snapshots.len(), 3);
+        assert!(snapshots.contains(&(Some("EUR".to_string()), Snapshot { available: 0.0, held: 100.0, locked: false, ..Default::default() })));
+        assert!(snapshots.contains(&(Some("GBP".to_string()), Snapshot { available: 30.0, held: 0.0, locked: false, ..Default::default() })));
+        assert_eq!(ledger.rejections.len(), 2);
+        assert_eq!(ledger.rejections[0].1, Rejection::InsufficientFunds);
+        assert_eq!(ledger.rejections[1].1, Rejection::CurrencyMismatch);
+        assert!(!ledger.accounts.contains_key(&2));
+    }
+
+    #[test]
+    fn test_rejected_rows_leave_no_currency_balance() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_rejected_currencies.csv",
+            "type,client,tx,amount,timestamp,currency,target_currency\n\
+            deposit,1,1,100.0,2026-09-29,EUR\n\
+            withdrawal,1,2,10.0,2026-09-29,GBP\n\
+            authorize,1,3,10.0,2026-09-29,USD\n\
+            convert,1,4,10.0,2026-09-29,CHF,EUR\n",
+        );
+        let mut config: Config = Config::default();
+        config.rates.insert("CHF", "EUR", 0, 1.05);
+        let ledger: Ledger = summarize_accounts(&file_path, config).unwrap();
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        assert_eq!(ledger.rejections.len(), 3);
+        assert!(ledger.rejections.iter().all(|(_, rejection)| *rejection == Rejection::InsufficientFunds));
+        assert_eq!(account.currencies.keys().collect::<Vec<&String>>(), vec!["EUR"]);
+        assert_eq!(account.snapshots().len(), 1);
+    }
+
+    #[test]
+    fn test_convert_between_currencies() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_convert.csv",
+            "type,client,tx,amount,timestamp,currency,target_currency\n\
+            deposit,1,1,100.0,2026-09-29,EUR\n\
+            convert,1,2,50.0,2026-09-29,EUR,GBP\n\
+            convert,1,3,20.0,2026-09-30,EUR,GBP\n\
+            convert,1,4,10.0,2026-09-30,GBP,EUR\n\
+            convert,1,5,100.0,2026-09-30,EUR,GBP\n\
+            convert,1,6,10.0,2026-09-30,EUR,USD\n\
+            convert,1,7,10.0,2026-09-30,EUR\n",
+        );
+        let mut config: Config = Config::default();
+        config.rates.insert("EUR", "GBP", 20_725, 0.8);
+        config.rates.insert("EUR", "GBP", 20_726, 0.5);
+        let ledger: Ledger = summarize_accounts(&file_path, config).unwrap();
+        let snapshots: Vec<(Option<String>, Snapshot)> = ledger.accounts.get(&1).unwrap().snapshots();
+
+        assert!(snapshots.contains(&(Some("EUR".to_string()), Snapshot { available: 50.0, held: 0.0, locked: false, ..Default::default() })));
+        assert!(snapshots.contains(&(Some("GBP".to_string()), Snapshot { available: 40.0, held: 0.0, locked: false, ..Default::default() })));
+        let reasons: Vec<Rejection> = ledger.rejections.into_iter().map(|(_, rejection)| rejection).collect();
+        assert_eq!(reasons, vec![Rejection::InsufficientFunds, Rejection::RateUnavailable, Rejection::MissingCurrency]);
+    }
+
+    #[test]
+    fn test_journal_trial_balance_sums_to_zero() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_journal.csv",
+            "type,client,tx,amount,timestamp,currency,target_currency\n\
+            deposit,1,1,100.0,,EUR\n\
+            deposit,1,2,30.0,,EUR\n\
+            withdrawal,1,3,10.0,,EUR\n\
+            authorize,1,4,20.0,,EUR\n\
+            capture,1,4,15.0\n\
+            convert,1,5,40.0,,EUR,GBP\n\
+            dispute,1,2\n\
+            chargeback,1,2\n\
+            deposit,2,6,5.0\n\
+            withdrawal,2,7,50.0\n",
+        );
+        let mut config: Config = Config {
+            fx_spread: 0.1,
+            ..Default::default()
+        };
+        config.rates.insert("EUR", "GBP", 0, 0.5);
+        let ledger: Ledger = summarize_accounts(&file_path, config).unwrap();
+        let balances: BTreeMap<(LedgerAccount, Option<String>), f32> = ledger.journal.trial_balance();
+        let euro: Option<String> = Some("EUR".to_string());
+        let pound: Option<String> = Some("GBP".to_string());
+
+        for currency in [None, euro.clone(), pound.clone()] {
+            let total: f32 = balances.iter()
+                .filter(|((_, balance_currency), _)| *balance_currency == currency)
+                .map(|(_, balance)| balance)
+                .sum();
+            assert_eq!(total, 0.0);
+        }
+        assert_eq!(balances.get(&(LedgerAccount::ClientAvailable(1), euro.clone())), Some(&-35.0));
+        assert_eq!(balances.get(&(LedgerAccount::ClientHeld(1), euro.clone())), Some(&0.0));
+        assert_eq!(balances.get(&(LedgerAccount::Settlement, euro.clone())), Some(&35.0));
+        assert_eq!(balances.get(&(LedgerAccount::ChargebackLoss, euro)), Some(&0.0));
+        assert_eq!(balances.get(&(LedgerAccount::ClientAvailable(1), pound.clone())), Some(&-18.0));
+        assert_eq!(balances.get(&(LedgerAccount::Fees, pound)), Some(&-2.0));
+        assert_eq!(balances.get(&(LedgerAccount::ClientAvailable(2), None)), Some(&-5.0));
+    }
+
+    #[test]
+    fn test_reconcile_after_every_kind_of_transaction() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_reconcile.csv",
+            "type,client,tx,amount,timestamp,currency,target_currency\n\
+            deposit,1,1,100.0,,EUR\n\
+            deposit,1,2,30.0,,EUR\n\
+            withdrawal,1,3,10.0,,EUR\n\
+            authorize,1,4,20.0,,EUR\n\
+            capture,1,4,15.0\n\
+            authorize,1,5,5.0,,EUR\n\
+            convert,1,6,40.0,,EUR,GBP\n\
+            deposit,2,7,10.0\n\
+            deposit,2,8,20.0\n\
+            dispute,2,7\n\
+            dispute,2,8\n\
+            resolve,2,8\n\
+            deposit,3,9,50.0\n\
+            dispute,3,9\n\
+            chargeback,3,9\n",
+        );
+        let mut config: Config = Config {
+            fx_spread: 0.1,
+            ..Default::default()
+        };
+        config.rates.insert("EUR", "GBP", 0, 0.5);
+        let mut ledger: Ledger = summarize_accounts(&file_path, config).unwrap();
+
+        assert!(reconcile(&ledger).is_empty());
+
+        ledger.accounts.get_mut(&2).unwrap().held += 1.0;
+        let checks: Vec<&str> = reconcile(&ledger).iter().map(|discrepancy| discrepancy.check).collect();
+        assert_eq!(checks, vec!["funds", "held", "journal_held"]);
+    }
+
+    #[test]
+    fn test_strict_mode_stops_at_first_violation() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_strict.csv",
+            "type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            withdrawal,1,2,40.0\n\
+            dispute,1,1\n\
+            deposit,2,3,10.0\n",
+        );
+        let strict: Config = Config {
+            strict: true,
+            ..Default::default()
+        };
+        let ledger: Ledger = summarize_accounts(&file_path, strict).unwrap();
+        assert!(ledger.violation.is_none());
+
+        let mut ledger: Ledger = Ledger::new(Config {
+            strict: true,
+            ..Default::default()
+        });
+        ledger.accounts.insert(1, Account {
+            available: 5.0,
+            ..Default::default()
+        });
+        let mut transactions: Vec<Transaction> = vec![
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 1,
+                amount: Some(10.0),
+                ..Default::default()
+            },
+            Transaction {
+                tx_type: "deposit".to_string(),
+                client_id: 1,
+                tx: 2,
+                amount: Some(1.0),
+                ..Default::default()
+            },
+        ];
+        process_multiple_transactions(&mut transactions, &mut ledger);
+
+        let first_violation: Option<String> = Some("Invariant violated after row 1 (deposit client 1 tx 1): default available 15.0000 plus held 0.0000 is 15.0000 but the history gives 10.0000".to_string());
+        assert_eq!(ledger.violation, first_violation);
+
+        // Reading stops at the row that broke an invariant
+        let mut ledger: Ledger = Ledger::new(Config {
+            strict: true,
+            reorder_window: 2,
+            ..Default::default()
+        });
+        ledger.accounts.insert(1, Account {
+            available: 5.0,
+            ..Default::default()
+        });
+        let ledger: Ledger = summarize_into(
+            "type,client,tx,amount\n\
+            deposit,1,1,10.0\n\
+            deposit,1,2,1.0\n\
+            deposit,2,3,1.0\n\
+            deposit,2,4,1.0\n".as_bytes(),
+            ledger,
+        );
+        assert_eq!(ledger.violation, first_violation);
+        assert_eq!(ledger.accounts.get(&1).unwrap().transactions.len(), 1);
+        assert!(!ledger.accounts.contains_key(&2));
+    }
+
+    #[test]
+    fn test_malformed_rows_are_skipped() {
+        let contents: &[u8] = b"type,client,tx,amount,timestamp\n\
+            deposit,1,1,NaN\n\
+            deposit,1,2,inf\n\
+            deposit,1,3,10.0,9223372036854775807\n\
+            deposit,\xff,4,1.0\n\
+            authorize,1,5,5.0,9223372036854775807\n\
+            \"unterminated,1,6,1.0\n";
+        let ledger: Ledger = summarize_reader(contents, Config {
+            authorization_expiry_secs: Some(60),
+            ..Default::default()
+        });
+
+        check_account(ledger.accounts.get(&1).unwrap(), 5.0, 5.0, false);
+        assert!(summarize_accounts("rust_ledger_missing_file.csv", Config::default()).is_err());
+    }
+
+    #[test]
+    fn test_padded_empty_amount_is_treated_as_missing() {
+        let contents: &[u8] = b"type,client,tx,amount\n\
+            deposit,1,1,10.0\n\
+            dispute , 1 , 1 ,  \n";
+        let ledger: Ledger = summarize_reader(contents, Config::default());
+
+        check_account(ledger.accounts.get(&1).unwrap(), 0.0, 10.0, false);
+    }
+
+    #[test]
+    fn test_get_float_blank_fields() {
+        let record: StringRecord = StringRecord::from(vec!["", "   ", " 1.5 ", "abc"]);
+
+        assert_eq!(get_float(&record, 0), Ok(None));
+        assert_eq!(get_float(&record, 1), Ok(None));
+        assert_eq!(get_float(&record, 2), Ok(Some(1.5)));
+        assert_eq!(get_float(&record, 3), Err(()));
+        assert_eq!(get_float(&record, 4), Ok(None));
+    }
+
+    #[test]
+    fn test_generated_file_matches_expected_balances() {
+        let options: GeneratorOptions = GeneratorOptions {
+            clients: 20,
+            rows: 5_000,
+            dispute_rate: 0.05,
+            resolve_rate: 0.02,
+            chargeback_rate: 0.01,
+            ..Default::default()
+        };
+        let mut contents: Vec<u8> = vec![];
+        let expected: BTreeMap<u16, Snapshot> = generate(&options, &mut contents).unwrap();
+        let ledger: Ledger = summarize_reader(contents.as_slice(), Config::default());
+        let actual: BTreeMap<u16, Snapshot> = ledger.accounts.iter()
+            .map(|(client_id, account)| (*client_id, account.snapshot(&None)))
+            .collect();
+
+        assert_eq!(actual, expected);
+        assert!(expected.values().any(|balance| balance.locked));
+        assert!(expected.values().any(|balance| balance.held > 0.0));
+    }
+
+    #[test]
+    fn test_bounded_memory_matches_full_history() {
+        let options: GeneratorOptions = GeneratorOptions {
+            clients: 5,
+            rows: 5_000,
+            dispute_rate: 0.05,
+            resolve_rate: 0.03,
+            chargeback_rate: 0.002,
+            ..Default::default()
+        };
+        let mut contents: Vec<u8> = vec![];
+        let expected: BTreeMap<u16, Snapshot> = generate(&options, &mut contents).unwrap();
+        let file_path: String = write_transactions_file("rust_ledger_test_bounded.csv", &String::from_utf8(contents).unwrap());
+        let spill_path: String = std::env::temp_dir().join("rust_ledger_test_bounded_spill.csv").to_str().unwrap().to_string();
+
+        let full: Ledger = summarize_accounts(&file_path, Config::default()).unwrap();
+        let bounded: Ledger = summarize_accounts(&file_path, Config {
+            retain_rows: Some(5_000),
+            spill_path: Some(spill_path.clone()),
+            ..Default::default()
+        }).unwrap();
+        let balances: BTreeMap<u16, Snapshot> = bounded.accounts.iter()
+            .map(|(client_id, account)| (*client_id, account.snapshot(&None)))
+            .collect();
+        let full_history: usize = full.accounts.values().map(|account| account.transactions.len()).sum();
+        let bounded_history: usize = bounded.accounts.values().map(|account| account.transactions.len()).sum();
+        let spilled: usize = std::fs::read_to_string(&spill_path).unwrap().lines().count() - 1;
+
+        assert_eq!(balances, expected);
+        assert_eq!(reconcile(&bounded), reconcile(&full));
+        assert!(bounded.journal.lines.is_empty());
+        assert_eq!(bounded.journal.trial_balance(), full.journal.trial_balance());
+        assert!(bounded_history < full_history);
+        assert_eq!(bounded_history + spilled, full_history);
+    }
+
+    #[test]
+    fn test_bounded_memory_drops_deposits_outside_window() {
+        let contents: &[u8] = b"type,client,tx,amount\n\
+            deposit,1,1,10.0\n\
+            deposit,1,2,20.0\n\
+            dispute,1,2,\n\
+            withdrawal,1,3,1.0\n\
+            withdrawal,1,4,1.0\n";
+        let mut rows: String = String::from_utf8(contents.to_vec()).unwrap();
+        for tx in 5..25 {
+            rows.push_str(&format!("deposit,1,{},1.0\n", tx));
+        }
+        rows.push_str("dispute,1,1,\nresolve,1,2,\n");
+        let ledger: Ledger = summarize_reader(rows.as_bytes(), Config {
+            retain_rows: Some(10),
+            ..Default::default()
+        });
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 48.0, 0.0, false);
+        assert!(account.transactions.len() < 26);
+        assert!(reconcile(&ledger).is_empty());
+    }
+
+    #[test]
+    fn test_bounded_memory_keeps_no_authorization_queue_without_expiry() {
+        let mut rows: String = String::from("type,client,tx,amount\n");
+        for tx in 1..=100 {
+            rows.push_str(&format!("deposit,1,{},1.0\nauthorize,1,{},1.0\n", 2 * tx, 2 * tx + 1));
+        }
+        let bounded: Ledger = summarize_reader(rows.as_bytes(), Config {
+            retain_rows: Some(10),
+            ..Default::default()
+        });
+        let expiring: Ledger = summarize_reader(rows.as_bytes(), Config {
+            retain_rows: Some(10),
+            authorization_expiry_rows: Some(1_000),
+            ..Default::default()
+        });
+
+        check_account(bounded.accounts.get(&1).unwrap(), 0.0, 100.0, false);
+        assert!(bounded.pending_authorizations.is_empty());
+        assert_eq!(expiring.pending_authorizations.len(), 100);
+    }
+
+    #[test]
+    fn test_dispute_window_in_rows() {
+        let contents: &[u8] = b"type,client,tx,amount\n\
+            deposit,1,1,10.0\n\
+            deposit,1,2,20.0\n\
+            deposit,1,3,30.0\n\
+            dispute,1,2,\n\
+            dispute,1,1,\n";
+        let ledger: Ledger = summarize_reader(contents, Config {
+            dispute_window_rows: Some(2),
+            ..Default::default()
+        });
+
+        check_account(ledger.accounts.get(&1).unwrap(), 40.0, 20.0, false);
+        assert_eq!(ledger.rejections.len(), 1);
+        assert_eq!(ledger.rejections[0].0.tx, 1);
+        assert_eq!(ledger.rejections[0].1, Rejection::DisputeWindowExpired);
+    }
+
+    #[test]
+    fn test_dispute_window_in_seconds() {
+        let contents: &[u8] = b"type,client,tx,amount,timestamp\n\
+            deposit,1,1,10.0,2026-09-01T10:00:00Z\n\
+            deposit,1,2,20.0,2026-09-20T10:00:00Z\n\
+            deposit,1,3,30.0\n\
+            dispute,1,1,,2026-10-01T10:00:00Z\n\
+            dispute,1,2,,2026-10-01T10:00:00Z\n\
+            dispute,1,3,,2026-10-01T10:00:00Z\n";
+        let ledger: Ledger = summarize_reader(contents, Config {
+            dispute_window_secs: Some(14 * 86400),
+            ..Default::default()
+        });
+
+        check_account(ledger.accounts.get(&1).unwrap(), 10.0, 50.0, false);
+        assert_eq!(ledger.rejections.len(), 1);
+        assert_eq!(ledger.rejections[0].1, Rejection::DisputeWindowExpired);
+    }
+
+    #[test]
+    fn test_deposits_past_dispute_window_are_pruned() {
+        let mut rows: String = "type,client,tx,amount\n".to_string();
+        for tx in 1..=40 {
+            rows.push_str(&format!("deposit,1,{},1.0\n", tx));
+        }
+        let ledger: Ledger = summarize_reader(rows.as_bytes(), Config {
+            dispute_window_rows: Some(5),
+            retain_rows: Some(1_000),
+            ..Default::default()
+        });
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        assert!(account.transactions.len() < 20);
+        assert!(account.transactions.iter().all(|transaction| transaction.tx > 20));
+        assert!(reconcile(&ledger).is_empty());
+    }
+
+    #[test]
+    fn test_partial_disputes_and_chargeback() {
+        let contents: &[u8] = b"type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            dispute,1,1,30.0\n\
+            dispute,1,1,20.0\n\
+            dispute,1,1,60.0\n\
+            resolve,1,1,10.0\n\
+            resolve,1,1,50.0\n\
+            chargeback,1,1,15.0\n";
+        let ledger: Ledger = summarize_reader(contents, Config {
+            strict: true,
+            ..Default::default()
+        });
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+        let deposit: &Transaction = &account.transactions[0];
+
+        check_account(account, 60.0, 25.0, true);
+        assert_eq!(deposit.disputed_amount, 25.0);
+        assert_eq!(deposit.charged_back_amount, 15.0);
+        assert!(deposit.disputed && deposit.charged_back);
+        assert_eq!(
+            ledger.rejections.iter().map(|(_, rejection)| rejection).collect::<Vec<&Rejection>>(),
+            vec![&Rejection::DisputeAmountExceeded, &Rejection::DisputeAmountExceeded]
+        );
+        assert_eq!(ledger.violation, None);
+        assert!(reconcile(&ledger).is_empty());
+    }
+
+    #[test]
+    fn test_dispute_without_amount_holds_the_rest_of_a_deposit() {
+        let contents: &[u8] = b"type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            dispute,1,1,30.0\n\
+            dispute,1,1,\n\
+            dispute,1,1,\n";
+        let ledger: Ledger = summarize_reader(contents, Config::default());
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 0.0, 100.0, false);
+        assert_eq!(ledger.rejections.len(), 1);
+        assert_eq!(ledger.rejections[0].1, Rejection::NoMatchingTransaction);
+
+        let resolved: Ledger = summarize_reader(b"type,client,tx,amount\n\
+            deposit,1,1,0.3\n\
+            dispute,1,1,0.1\n\
+            dispute,1,1,0.2\n\
+            resolve,1,1,0.2\n\
+            resolve,1,1,0.1\n".as_slice(), Config::default());
+        let account: &Account = resolved.accounts.get(&1).unwrap();
+
+        assert_eq!(account.held, 0.0);
+        assert!(!account.transactions[0].disputed);
+        assert_eq!(account.transactions[0].disputed_amount, 0.0);
+    }
+
+    #[test]
+    fn test_zero_amount_dispute_is_rejected() {
+        let contents: &[u8] = b"type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            dispute,1,1,0\n\
+            chargeback,1,1,\n\
+            dispute,1,1,40.0\n\
+            chargeback,1,1,0.0\n";
+        let ledger: Ledger = summarize_reader(contents, Config::default());
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        check_account(account, 60.0, 40.0, false);
+        assert_eq!(
+            ledger.rejections.iter().map(|(_, rejection)| rejection).collect::<Vec<&Rejection>>(),
+            vec![&Rejection::ZeroDisputeAmount, &Rejection::NoMatchingTransaction, &Rejection::ZeroDisputeAmount]
+        );
+    }
+
+    #[test]
+    fn test_redisputes_are_limited_and_recorded() {
+        let contents: &[u8] = b"type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            dispute,1,1,\n\
+            resolve,1,1,\n\
+            dispute,1,1,\n\
+            resolve,1,1,\n\
+            dispute,1,1,\n\
+            deposit,2,2,10.0\n\
+            dispute,2,2,\n";
+        let ledger: Ledger = summarize_reader(contents, Config {
+            max_redisputes: Some(1),
+            ..Default::default()
+        });
+        let records: Vec<&DisputeRecord> = ledger.dispute_records(None);
+        let events: Vec<DisputeEventKind> = records[0].events.iter().map(|event| event.kind).collect();
+
+        check_account(ledger.accounts.get(&1).unwrap(), 100.0, 0.0, false);
+        assert_eq!(ledger.rejections.len(), 1);
+        assert_eq!(ledger.rejections[0].1, Rejection::RedisputeLimitExceeded);
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].id, records[0].redisputes, records[0].status), (1, 1, DisputeStatus::Resolved));
+        assert_eq!(events, vec![
+            DisputeEventKind::Opened,
+            DisputeEventKind::Resolved,
+            DisputeEventKind::Reopened,
+            DisputeEventKind::Resolved,
+        ]);
+        assert_eq!((records[1].id, records[1].status), (2, DisputeStatus::Open));
+        assert_eq!(ledger.dispute_records(Some(2)).len(), 1);
+    }
+
+    #[test]
+    fn test_chargeback_reversal_restores_funds() {
+        let contents: &str = "type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            deposit,1,2,50.0\n\
+            dispute,1,2,\n\
+            chargeback,1,2,\n\
+            chargeback_reversal,1,1,\n\
+            chargeback_reversal,1,2,20.0\n\
+            deposit,1,3,5.0\n\
+            chargeback_reversal,1,2,\n\
+            deposit,1,4,5.0\n";
+
+        let locked: Ledger = summarize_reader(contents.as_bytes(), Config { strict: true, ..Default::default() });
+        let account: &Account = locked.accounts.get(&1).unwrap();
+        check_account(account, 150.0, 0.0, true);
+        assert!(!account.transactions[1].charged_back);
+        assert_eq!(
+            locked.rejections.iter().map(|(transaction, rejection)| (transaction.tx, rejection)).collect::<Vec<(u32, &Rejection)>>(),
+            vec![(1, &Rejection::NoMatchingTransaction), (3, &Rejection::AccountLocked), (4, &Rejection::AccountLocked)]
+        );
+        assert_eq!(locked.violation, None);
+        assert!(reconcile(&locked).is_empty());
+
+        let unlocked: Ledger = summarize_reader(contents.as_bytes(), Config { unlock_on_reversal: true, ..Default::default() });
+        let account: &Account = unlocked.accounts.get(&1).unwrap();
+        check_account(account, 155.0, 0.0, false);
+        assert_eq!(account.chargebacks, 0);
+        assert_eq!(unlocked.dispute_records(Some(1))[0].status, DisputeStatus::Reversed);
+        assert!(reconcile(&unlocked).is_empty());
+    }
+
+    #[test]
+    fn test_lock_policy() {
+        let contents: &str = "type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            deposit,1,2,300.0\n\
+            deposit,1,3,50.0\n\
+            dispute,1,1,\n\
+            chargeback,1,1,\n\
+            dispute,1,2,\n\
+            chargeback,1,2,\n\
+            dispute,1,3,\n\
+            chargeback,1,3,\n\
+            deposit,1,4,10.0\n";
+        let run = |lock_policy: LockPolicy| summarize_reader(contents.as_bytes(), Config { lock_policy, ..Default::default() });
+
+        let always: Ledger = run(LockPolicy::Always);
+        let account: &Account = always.accounts.get(&1).unwrap();
+        check_account(account, 350.0, 0.0, true);
+        assert_eq!((account.chargebacks, account.flagged), (1, false));
+
+        let after: Ledger = run(LockPolicy::AfterChargebacks(2));
+        let account: &Account = after.accounts.get(&1).unwrap();
+        check_account(account, 50.0, 0.0, true);
+        assert_eq!((account.chargebacks, account.flagged), (2, true));
+
+        let above: Ledger = run(LockPolicy::AboveAmount(200.0));
+        let account: &Account = above.accounts.get(&1).unwrap();
+        check_account(account, 50.0, 0.0, true);
+        assert_eq!((account.chargebacks, account.flagged), (2, true));
+
+        let never: Ledger = run(LockPolicy::Never);
+        let account: &Account = never.accounts.get(&1).unwrap();
+        check_account(account, 10.0, 0.0, false);
+        assert_eq!((account.chargebacks, account.flagged), (3, true));
+        assert_eq!(account.snapshot(&None).chargebacks, 3);
+        assert!(never.rejections.is_empty());
+        assert!(reconcile(&never).is_empty());
+    }
+
+    #[test]
+    fn test_reversal_unlocks_under_lock_policy() {
+        let contents: &str = "type,client,tx,amount\n\
+            deposit,1,1,100.0\n\
+            deposit,1,2,300.0\n\
+            dispute,1,1,\n\
+            chargeback,1,1,\n\
+            dispute,1,2,\n\
+            chargeback,1,2,\n\
+            chargeback_reversal,1,1,\n\
+            deposit,1,3,10.0\n";
+        let run = |lock_policy: LockPolicy| summarize_reader(contents.as_bytes(), Config {
+            lock_policy,
+            unlock_on_reversal: true,
+            ..Default::default()
+        });
+
+        // Reversing either chargeback leaves one standing, which `after:2` does not lock on
+        let after: Ledger = run(LockPolicy::AfterChargebacks(2));
+        let account: &Account = after.accounts.get(&1).unwrap();
+        check_account(account, 110.0, 0.0, false);
+        assert_eq!(account.chargebacks, 1);
+        assert!(after.rejections.is_empty());
+
+        // The chargeback still standing is above the threshold
+        let above: Ledger = run(LockPolicy::AboveAmount(200.0));
+        let account: &Account = above.accounts.get(&1).unwrap();
+        check_account(account, 100.0, 0.0, true);
+        assert_eq!(account.chargebacks, 1);
+
+        // The first chargeback locks the account, so the second is never applied
+        let always: Ledger = run(LockPolicy::Always);
+        let account: &Account = always.accounts.get(&1).unwrap();
+        check_account(account, 410.0, 0.0, false);
+        assert_eq!(account.chargebacks, 0);
+        assert_eq!(always.rejections.len(), 2);
+    }
+
+    #[test]
+    fn test_header_driven_columns() {
+        let contents: &str = "Amount,note,TXN,client_id,type,currency\n\
+            100.0,first,1,1,deposit,EUR\n\
+            40.0,,2,1,withdrawal,EUR\n\
+            5.5,,3,1,deposit,\n";
+        let ledger: Ledger = summarize_reader(contents.as_bytes(), Config::default());
+        let account: &Account = ledger.accounts.get(&1).unwrap();
+
+        assert_eq!(ledger.input_error, None);
+        assert_eq!(account.snapshot(&Some("EUR".to_string())).available, 60.0);
+        check_account(account, 5.5, 0.0, false);
+
+        let contents: &str = "kind,client,reference,amount\ndeposit,1,1,100.0\n";
+        let aliased: Ledger = summarize_reader(contents.as_bytes(), Config {
+            column_aliases: HashMap::from([
+                ("kind".to_string(), "type".to_string()),
+                ("reference".to_string(), "tx".to_string()),
+            ]),
+            ..Default::default()
+        });
+        check_account(aliased.accounts.get(&1).unwrap(), 100.0, 0.0, false);
+    }
+
+    #[test]
+    fn test_missing_required_column() {
+        let file_path: String = write_transactions_file(
+            "rust_ledger_test_missing_column.csv",
+            "type,client,reference,amount\ndeposit,1,1,100.0\n",
+        );
+        let ledger: Ledger = summarize_reader("type,client,amount\ndeposit,1,100.0\n".as_bytes(), Config::default());
+
+        assert!(ledger.accounts.is_empty());
+        assert_eq!(ledger.input_error, Some("no tx column".to_string()));
+        assert_eq!(
+            summarize_accounts(&file_path, Config::default()).err(),
+            Some(format!("Transactions file {} has no tx column", file_path))
+        );
+    }
+
+    #[test]
+    fn test_csv_dialects() {
+        let contents: &str = "deposit\t1\t1\t\"100.0\"\n\
+            withdrawal\t1\t2\t40.0\n";
+        let ledger: Ledger = summarize_reader(contents.as_bytes(), Config {
+            dialect: Dialect {
+                delimiter: b'\t',
+                quote: None,
+                has_headers: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        // Without quoting the quoted amount does not parse, so only the withdrawal is read
+        assert_eq!(ledger.row, 1);
+        assert_eq!(ledger.rejections[0].1, Rejection::InsufficientFunds);
+
+        let contents: &str = "\u{feff}type|client|tx|amount\n\
+            deposit|1|1|100,50\n\
+            deposit|1|2|0,25\n\
+            deposit|1|3|1.000,00\n\
+            deposit|1|4|10.00,0\n\
+            deposit|1|5|2.5\n";
+        let ledger: Ledger = summarize_reader(contents.as_bytes(), Config {
+            dialect: Dialect {
+                delimiter: b'|',
+                decimal_comma: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        // Dots are thousands separators and must group the digits in threes
+        check_account(ledger.accounts.get(&1).unwrap(), 1100.75, 0.0, false);
+        assert_eq!(ledger.row, 3);
+        assert_eq!(ledger.input_error, None);
+    }
+
+    #[test]
+    fn test_json_lines_input_matches_csv() {
+        let csv: &str = "type,client,tx,amount,timestamp\n\
+            deposit,1,1,100.0,2026-09-29\n\
+            deposit,2,2,30.5,2026-09-29\n\
+            withdrawal,1,3,20.0,2026-09-30\n\
+            dispute,2,2,,2026-09-30\n\
+            withdrawal,2,4,1.0,2026-10-01\n";
+        let json_lines: &str = "{\"type\": \"deposit\", \"client\": 1, \"tx\": 1, \"amount\": 100.0, \"timestamp\": \"2026-09-29\"}\n\
+            {\"type\": \"deposit\", \"client\": 2, \"tx\": 2, \"amount\": \"30.5\", \"timestamp\": \"2026-09-29\"}\n\
+            \n\
+            {\"type\": \"withdrawal\", \"client\": 1, \"tx\": 3, \"amount\": 20, \"timestamp\": \"2026-09-30\"\n\
+            {\"type\": \"withdrawal\", \"client\": 1, \"tx\": 3, \"amount\": 20, \"timestamp\": \"2026-09-30\"}\n\
+            {\"type\": \"dispute\", \"client\": 2, \"tx\": 2, \"timestamp\": \"2026-09-30\"}\n\
+            {\"type\": \"withdrawal\", \"client\": 2, \"tx\": 4, \"amount\": 1.0, \"timestamp\": \"2026-10-01\"}";
+        let from_csv: Ledger = summarize_reader(csv.as_bytes(), Config::default());
+        let from_json: Ledger = summarize_reader(json_lines.as_bytes(), Config {
+            input_format: InputFormat::JsonLines,
+            ..Default::default()
+        });
+
+        assert_eq!(from_json.row, 5);
+        assert_eq!(from_json.rejections.len(), 1);
+        assert_eq!(from_json.rejections[0].1, Rejection::InsufficientFunds);
+        for (client_id, account) in from_csv.accounts.iter() {
+            let json_account: &Account = from_json.accounts.get(client_id).unwrap();
+            assert_eq!(json_account.snapshot(&None), account.snapshot(&None));
+        }
+        assert_eq!(from_json.accounts.len(), from_csv.accounts.len());
+    }
 }
@@ -1,51 +1,187 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use csv::{StringRecord};
+use crate::columns::ColumnMap;
+use crate::config::AccountLimits;
+use crate::dates::parse_timestamp;
+use crate::dialects::Dialect;
+use crate::disputes::DisputeRecord;
 
+#[derive(Clone, Default)]
 pub struct Transaction {
     pub tx_type: String,
     pub client_id: u16,
     pub tx: u32,
     pub amount: Option<f32>,
     pub disputed: bool,
+    pub charged_back: bool,
+    // On deposits, the parts currently held under dispute and already charged back
+    pub disputed_amount: f32,
+    pub charged_back_amount: f32,
+    pub authorized: bool,
+    pub row: u64,
+    pub timestamp: Option<i64>,
+    pub currency: Option<String>,
+    pub target_currency: Option<String>,
+    pub snapshot: Snapshot,
 }
 
-impl Clone for Transaction {
-    fn clone(&self) -> Self {
-        Transaction {
-            tx_type: self.tx_type.clone(),
-            client_id: self.client_id,
-            tx: self.tx,
-            amount: self.amount,
-            disputed: self.disputed,
-        }
-    }
+// The state of the account, in the transaction's currency, immediately after the
+// transaction was applied
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub available: f32,
+    pub held: f32,
+    pub locked: bool,
+    pub chargebacks: u32,
+    pub flagged: bool,
 }
 
+#[derive(Default)]
 pub struct Account {
     pub available: f32,
     pub held: f32,
     pub locked: bool,
     pub transactions: Vec<Transaction>,
+    pub currencies: BTreeMap<String, Balance>,
+    pub limits: AccountLimits,
+    // Deposits currently charged back, and whether a chargeback left the account
+    // unlocked under the lock policy
+    pub chargebacks: u32,
+    pub flagged: bool,
+    pub daily_withdrawn: f32,
+    pub withdrawal_day: Option<i64>,
+    pub recent_withdrawals: VecDeque<u64>,
+    // Funds in each currency as derived from the history, kept for strict mode
+    pub history_totals: BTreeMap<Option<String>, f32>,
+    // Funds moved by history entries dropped in bounded-memory mode, and how many
+    // entries were kept by the last pruning
+    pub pruned_funds: BTreeMap<Option<String>, f32>,
+    pub retained_history: usize,
+    // Dispute records keyed by the tx of the disputed deposit
+    pub disputes: BTreeMap<u32, DisputeRecord>,
+}
+
+// Funds held in an explicit currency. `Account.available` and `Account.held` hold
+// the funds of transactions that do not name a currency.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Balance {
+    pub available: f32,
+    pub held: f32,
+}
+
+impl Account {
+    pub fn snapshot(&self, currency: &Option<String>) -> Snapshot {
+        let balance: Balance = match currency {
+            Some(currency) => self.currencies.get(currency).copied().unwrap_or_default(),
+            None => Balance {
+                available: self.available,
+                held: self.held,
+            },
+        };
+        Snapshot {
+            available: balance.available,
+            held: balance.held,
+            locked: self.locked,
+            chargebacks: self.chargebacks,
+            flagged: self.flagged,
+        }
+    }
+
+    // The balance without a currency is left out once the account trades in
+    // explicit currencies, unless it still holds funds
+    pub fn snapshots(&self) -> Vec<(Option<String>, Snapshot)> {
+        let mut snapshots: Vec<(Option<String>, Snapshot)> = vec![];
+        if self.currencies.is_empty() || self.available != 0.0 || self.held != 0.0 {
+            snapshots.push((None, self.snapshot(&None)));
+        }
+        for currency in self.currencies.keys() {
+            let currency: Option<String> = Some(currency.clone());
+            let snapshot: Snapshot = self.snapshot(&currency);
+            snapshots.push((currency, snapshot));
+        }
+        snapshots
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Rejection {
+    AccountLocked,
+    MissingAmount,
+    InsufficientFunds,
+    NoMatchingTransaction,
+    CurrencyMismatch,
+    MissingCurrency,
+    RateUnavailable,
+    SameCurrencyConversion,
+    FeeExceedsConversion,
+    UnknownType,
+    MaxWithdrawalExceeded,
+    DailyWithdrawalLimitExceeded,
+    WithdrawalCountLimitExceeded,
+    DisputeWindowExpired,
+    DisputeAmountExceeded,
+    ZeroDisputeAmount,
+    RedisputeLimitExceeded,
+    CaptureExceedsAuthorization,
+}
+
+impl Rejection {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Rejection::AccountLocked => "account_locked",
+            Rejection::MissingAmount => "missing_amount",
+            Rejection::InsufficientFunds => "insufficient_funds",
+            Rejection::NoMatchingTransaction => "no_matching_transaction",
+            Rejection::CurrencyMismatch => "currency_mismatch",
+            Rejection::MissingCurrency => "missing_currency",
+            Rejection::RateUnavailable => "rate_unavailable",
+            Rejection::SameCurrencyConversion => "same_currency_conversion",
+            Rejection::FeeExceedsConversion => "fee_exceeds_conversion",
+            Rejection::UnknownType => "unknown_type",
+            Rejection::MaxWithdrawalExceeded => "max_withdrawal_exceeded",
+            Rejection::DailyWithdrawalLimitExceeded => "daily_withdrawal_limit_exceeded",
+            Rejection::WithdrawalCountLimitExceeded => "withdrawal_count_limit_exceeded",
+            Rejection::DisputeWindowExpired => "dispute_window_expired",
+            Rejection::DisputeAmountExceeded => "dispute_amount_exceeded",
+            Rejection::ZeroDisputeAmount => "zero_dispute_amount",
+            Rejection::RedisputeLimitExceeded => "redispute_limit_exceeded",
+            Rejection::CaptureExceedsAuthorization => "capture_exceeds_authorization",
+        }
+    }
 }
 
-pub fn process_row(result: StringRecord) -> Result<(String, u16, u32, Option<f32>), ()> {
+pub fn process_row(result: StringRecord, columns: &ColumnMap, dialect: &Dialect) -> Result<Transaction, ()> {
     let record: StringRecord = result;
-    let tx_type: String = get_string(&record)?;
-    let client_id: u16 = get_int_u16(&record, 1)?;
-    let tx: u32 = get_int_u32(&record, 2)?;
-    let amount: Option<f32>= get_float(&record)?;
+    let tx_type: String = get_string(&record, columns.tx_type)?;
+    let client_id: u16 = get_int_u16(&record, columns.client)?;
+    let tx: u32 = get_int_u32(&record, columns.tx)?;
+    let amount: Option<f32>= get_amount(&record, columns.amount, dialect.decimal_comma)?;
+    let timestamp: Option<i64> = get_optional(&record, columns.timestamp, get_timestamp)?;
+    let currency: Option<String> = get_optional(&record, columns.currency, get_currency)?;
+    let target_currency: Option<String> = get_optional(&record, columns.target_currency, get_currency)?;
     
-    Ok((tx_type, client_id, tx, amount))
+    Ok(Transaction {
+        tx_type,
+        client_id,
+        tx,
+        amount,
+        timestamp,
+        currency,
+        target_currency,
+        ..Default::default()
+    })
 }
 
-fn get_string(record: &StringRecord) -> Result<String, ()> {
-    match record.get(0) {
+fn get_string(record: &StringRecord, index: usize) -> Result<String, ()> {
+    match record.get(index) {
         Some(tx_type) => Ok(tx_type.trim().to_string()),
         None => Err(()),
     }
 }
 
-fn get_int_u16(record: &StringRecord, index: usize) -> Result<u16, ()> {
+pub fn get_int_u16(record: &StringRecord, index: usize) -> Result<u16, ()> {
     match record.get(index) {
         Some(id) => {
             match id.trim().parse() {
@@ -58,7 +194,7 @@ fn get_int_u16(record: &StringRecord, index: usize) -> Result<u16, ()> {
     }
 }
 
-fn get_int_u32(record: &StringRecord, index: usize) -> Result<u32, ()> {
+pub fn get_int_u32(record: &StringRecord, index: usize) -> Result<u32, ()> {
     match record.get(index) {
         Some(id) => {
             match id.trim().parse() {
@@ -72,34 +208,134 @@ fn get_int_u32(record: &StringRecord, index: usize) -> Result<u32, ()> {
 }
 
 
-fn get_float(record: &StringRecord) -> Result<Option<f32>, ()> {
-    match record.get(3) {
-        Some(amount_str) if !amount_str.is_empty() => {
-            match amount_str.trim().parse() {
-                Ok(amount) => {
-                    if amount < 0.0 {
-                        return Err(());
-                    }
-                    Ok(Some(amount))
-                },
-                Err(_) => Err(()),
+pub fn get_float(record: &StringRecord, index: usize) -> Result<Option<f32>, ()> {
+    match record.get(index) {
+        Some(amount_str) if !amount_str.trim().is_empty() => parse_amount(amount_str.trim()).map(Some),
+        _ => Ok(None),
+    }
+}
+
+// With `decimal_comma`, a comma in the amount is read as the decimal point and dots
+// as thousands separators, e.g. `1.000,50`
+fn get_amount(record: &StringRecord, index: usize, decimal_comma: bool) -> Result<Option<f32>, ()> {
+    match record.get(index) {
+        Some(amount_str) if decimal_comma && !amount_str.trim().is_empty() => {
+            parse_amount(&from_decimal_comma(amount_str.trim())?).map(Some)
+        },
+        _ => get_float(record, index),
+    }
+}
+
+// Separators must split the whole number into groups of three digits after the first
+fn from_decimal_comma(amount_str: &str) -> Result<String, ()> {
+    let (whole, fraction) = match amount_str.split_once(',') {
+        Some((whole, fraction)) if !fraction.contains([',', '.']) => (whole, Some(fraction)),
+        Some(_) => return Err(()),
+        None => (amount_str, None),
+    };
+    let mut groups = whole.split('.');
+    let first: &str = groups.next().unwrap_or_default();
+    let mut digits: String = first.to_string();
+    for group in groups {
+        if first.is_empty() || first.len() > 3 || group.len() != 3 || !group.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(());
+        }
+        digits.push_str(group);
+    }
+    if let Some(fraction) = fraction {
+        digits.push('.');
+        digits.push_str(fraction);
+    }
+    Ok(digits)
+}
+
+fn parse_amount(amount_str: &str) -> Result<f32, ()> {
+    match amount_str.parse::<f32>() {
+        Ok(amount) => {
+            if amount < 0.0 || !amount.is_finite() {
+                return Err(());
             }
+            Ok(amount)
         },
+        Err(_) => Err(()),
+    }
+}
+
+fn get_timestamp(record: &StringRecord, index: usize) -> Result<Option<i64>, ()> {
+    match record.get(index) {
+        Some(timestamp_str) if !timestamp_str.trim().is_empty() => parse_timestamp(timestamp_str).map(Some),
         _ => Ok(None),
     }
 }
 
+// Reads a column the header may not have, which is then empty for every row
+fn get_optional<T>(
+    record: &StringRecord,
+    index: Option<usize>,
+    get: fn(&StringRecord, usize) -> Result<Option<T>, ()>,
+) -> Result<Option<T>, ()> {
+    match index {
+        Some(index) => get(record, index),
+        None => Ok(None),
+    }
+}
+
+pub fn get_currency(record: &StringRecord, index: usize) -> Result<Option<String>, ()> {
+    match record.get(index).map(|currency| currency.trim()) {
+        Some("") | None => Ok(None),
+        Some(currency) if currency.chars().all(|character| character.is_ascii_alphabetic()) => {
+            Ok(Some(currency.to_ascii_uppercase()))
+        },
+        Some(_) => Err(()),
+    }
+}
+
 pub fn output_accounts(accounts: HashMap<u16, Account>) {
-    println!("client,available,held,total,locked");
-    for (client_id, account) in accounts.iter() {
-        let total: f32 = account.available + account.held;
+    let balances: HashMap<(u16, Option<String>), Snapshot> = accounts.iter()
+        .flat_map(|(client_id, account)| {
+            account.snapshots().into_iter().map(|(currency, snapshot)| ((*client_id, currency), snapshot))
+        })
+        .collect();
+    output_balances(balances);
+}
+
+pub fn output_balances(balances: HashMap<(u16, Option<String>), Snapshot>) {
+    println!("client,available,held,total,locked,overdraft,currency,chargebacks,flagged");
+    for ((client_id, currency), balance) in balances.iter() {
+        let total: f32 = balance.available + balance.held;
+        let overdraft: f32 = if balance.available < 0.0 { -balance.available } else { 0.0 };
         println!(
-            "{},{:.4},{:.4},{:.4},{}", 
+            "{},{:.4},{:.4},{:.4},{},{:.4},{},{},{}", 
             client_id,
-            account.available, 
-            account.held, 
+            balance.available, 
+            balance.held, 
             total, 
-            account.locked
+            balance.locked,
+            overdraft,
+            currency.as_deref().unwrap_or(""),
+            balance.chargebacks,
+            balance.flagged
         );
     }
 }
+
+pub fn output_rejections(rejections: &[(Transaction, Rejection)], file_path: &str) -> std::io::Result<()> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(file_path)?);
+    writeln!(writer, "type,client,tx,amount,reason")?;
+    for (transaction, rejection) in rejections {
+        let amount: String = match transaction.amount {
+            Some(amount) => format!("{:.4}", amount),
+            None => String::new(),
+        };
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            transaction.tx_type,
+            transaction.client_id,
+            transaction.tx,
+            amount,
+            rejection.code()
+        )?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,154 @@
+// Golden-file tests that run the built binary over every `<name>.csv` in `tests/data`
+// that has a matching `<name>.expected.csv`. A `<name>.args` file may hold extra
+// arguments, such as a command and its options, which are passed before the input
+// file. Commands run from `tests/data`, so the arguments can name other files there.
+// Every case must exit successfully.
+//
+// Rows are compared after sorting everything below the header, since clients are
+// not printed in a fixed order. Run with `UPDATE_GOLDEN=1` to rewrite the expected
+// files from the current output.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+struct Case {
+    name: String,
+    input: PathBuf,
+    expected: PathBuf,
+    args: Vec<String>,
+}
+
+fn data_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("data")
+}
+
+fn discover_cases() -> Vec<Case> {
+    let root: &Path = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut cases: Vec<Case> = vec![Case {
+        name: "transactions".to_string(),
+        input: root.join("transactions.csv"),
+        expected: root.join("accounts.csv"),
+        args: vec![],
+    }];
+
+    let mut inputs: Vec<PathBuf> = std::fs::read_dir(data_dir())
+        .expect("tests/data should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "csv"))
+        .filter(|path| !path.to_string_lossy().ends_with(".expected.csv"))
+        .collect();
+    inputs.sort();
+
+    for input in inputs {
+        let name: String = input.file_stem().unwrap().to_string_lossy().to_string();
+        let expected: PathBuf = input.with_file_name(format!("{}.expected.csv", name));
+        if !expected.exists() {
+            continue;
+        }
+        let args: Vec<String> = match std::fs::read_to_string(input.with_file_name(format!("{}.args", name))) {
+            Ok(args) => args.split_whitespace().map(|arg| arg.to_string()).collect(),
+            Err(_) => vec![],
+        };
+        cases.push(Case { name, input, expected, args });
+    }
+    cases
+}
+
+// A case fails outright when the binary exits with an error, whatever it printed
+fn run_case(case: &Case) -> Result<String, String> {
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_RustLedger"))
+        .current_dir(data_dir())
+        .args(&case.args)
+        .arg(&case.input)
+        .output()
+        .expect("the binary should run");
+    if !output.status.success() {
+        return Err(format!("{}: exited with {}\n{}", case.name, output.status, String::from_utf8_lossy(&output.stderr).trim_end()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn sorted_lines(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = contents.lines()
+        .map(|line| line.trim_end().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.len() > 1 {
+        lines[1..].sort();
+    }
+    lines
+}
+
+// Both sides are sorted below the header, so a merge gives a minimal line diff
+fn diff(expected: &[String], actual: &[String]) -> Vec<String> {
+    let mut differences: Vec<String> = vec![];
+    let (mut expected_index, mut actual_index) = (0, 0);
+    if expected.first() != actual.first() {
+        differences.extend(expected.first().map(|line| format!("- {}", line)));
+        differences.extend(actual.first().map(|line| format!("+ {}", line)));
+    }
+    expected_index += 1;
+    actual_index += 1;
+    while expected_index < expected.len() || actual_index < actual.len() {
+        match (expected.get(expected_index), actual.get(actual_index)) {
+            (Some(expected_line), Some(actual_line)) if expected_line == actual_line => {
+                expected_index += 1;
+                actual_index += 1;
+            },
+            (Some(expected_line), Some(actual_line)) if expected_line < actual_line => {
+                differences.push(format!("- {}", expected_line));
+                expected_index += 1;
+            },
+            (_, Some(actual_line)) => {
+                differences.push(format!("+ {}", actual_line));
+                actual_index += 1;
+            },
+            (Some(expected_line), None) => {
+                differences.push(format!("- {}", expected_line));
+                expected_index += 1;
+            },
+            (None, None) => break,
+        }
+    }
+    differences
+}
+
+#[test]
+fn golden_files() {
+    let update: bool = std::env::var("UPDATE_GOLDEN").is_ok_and(|value| value == "1");
+    let mut failures: Vec<String> = vec![];
+
+    for case in discover_cases() {
+        let actual: String = match run_case(&case) {
+            Ok(actual) => actual,
+            Err(failure) => {
+                failures.push(failure);
+                continue;
+            },
+        };
+        let expected: String = std::fs::read_to_string(&case.expected).unwrap_or_default();
+        let differences: Vec<String> = diff(&sorted_lines(&expected), &sorted_lines(&actual));
+        if update && !differences.is_empty() {
+            std::fs::write(&case.expected, &actual).unwrap();
+        } else if !differences.is_empty() {
+            failures.push(format!(
+                "{} ({} vs {}):\n{}",
+                case.name,
+                case.input.display(),
+                case.expected.display(),
+                differences.join("\n")
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "golden files differ (- expected, + actual):\n\n{}", failures.join("\n\n"));
+}
+
+#[test]
+fn diff_reports_missing_and_unexpected_lines() {
+    let expected: Vec<String> = sorted_lines("client,available\n1,1.0\n2,2.0\n");
+    let actual: Vec<String> = sorted_lines("client,available\n3,3.0\n1,1.0\n");
+
+    assert_eq!(diff(&expected, &actual), vec!["- 2,2.0", "+ 3,3.0"]);
+    assert!(diff(&expected, &expected).is_empty());
+}